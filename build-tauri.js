@@ -6,7 +6,7 @@ import { fileURLToPath } from 'url';
 const __dirname = path.dirname(fileURLToPath(import.meta.url));
 
 const srcDir = path.join(__dirname, 'src');
-const rootFiles = ['index.html', 'bubble.html'];
+const rootFiles = ['index.html', 'bubble.html', 'region-select.html', 'highlight.html', 'typing-test.html', 'key-capture-indicator.html'];
 const destDir = path.join(__dirname, 'www');
 
 // Clean www/ directory (except loader files for backwards compat)
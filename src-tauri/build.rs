@@ -1,3 +1,55 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 fn main() {
+    // Embed build metadata for get_app_info
+    let git_commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_COMMIT={}", git_commit);
+    println!("cargo:rustc-env=BUILD_DATE={}", build_date());
+    watch_git_head();
+
     tauri_build::build()
 }
+
+// Rerun when HEAD moves, so incremental builds don't keep an old commit: on a
+// checkout HEAD itself changes, on a commit the branch ref it points to does
+fn watch_git_head() {
+    let Some(git_dir) = Command::new("git")
+        .args(["rev-parse", "--absolute-git-dir"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| std::path::PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+    else {
+        return;
+    };
+
+    let head = git_dir.join("HEAD");
+    println!("cargo:rerun-if-changed={}", head.display());
+    if let Some(reference) = std::fs::read_to_string(&head).ok().and_then(|text| text.strip_prefix("ref: ").map(|r| r.trim().to_string())) {
+        println!("cargo:rerun-if-changed={}", git_dir.join(reference).display());
+        // Refs move here after git gc
+        println!("cargo:rerun-if-changed={}", git_dir.join("packed-refs").display());
+    }
+}
+
+// Current UTC date as YYYY-MM-DD (days-to-civil conversion, no date crate needed)
+fn build_date() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -1,24 +1,277 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use enigo::{Enigo, Settings, Coordinate, Direction, Mouse, Keyboard, Button, Key};
 
 // Desktop automation commands
-#[tauri::command]
-fn run_shell_command(command: String) -> Result<String, String> {
+#[derive(Default)]
+struct ShellConfig {
+    // None means the platform default (cmd on Windows, sh elsewhere)
+    default_shell: Mutex<Option<String>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ShellKind {
+    Cmd,
+    PowerShell,
+    // sh, bash, zsh and anything else taking -c
+    Posix,
+}
+
+// The configured shell's path and how it parses commands
+fn configured_shell(config: &ShellConfig) -> (String, ShellKind) {
     #[cfg(target_os = "windows")]
-    let output = Command::new("cmd")
-        .args(["/C", &command])
-        .output()
+    let fallback = "cmd";
+    #[cfg(not(target_os = "windows"))]
+    let fallback = "sh";
+
+    let shell = config.default_shell.lock().unwrap().clone().unwrap_or(fallback.to_string());
+    let name = std::path::Path::new(&shell)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let kind = match name.as_str() {
+        "cmd" => ShellKind::Cmd,
+        "powershell" | "pwsh" => ShellKind::PowerShell,
+        _ => ShellKind::Posix,
+    };
+    (shell, kind)
+}
+
+// Build a command that runs `command` through the configured shell. The
+// command reaches the shell byte for byte: no argv quoting layer is added.
+fn shell_command(config: &ShellConfig, command: &str) -> Command {
+    use base64::Engine;
+
+    let (shell, kind) = configured_shell(config);
+    let mut cmd = Command::new(&shell);
+    match kind {
+        // Command::arg would escape embedded quotes as \", which cmd doesn't
+        // understand. /S makes cmd strip just the outer quotes and run the rest.
+        #[cfg(target_os = "windows")]
+        ShellKind::Cmd => {
+            use std::os::windows::process::CommandExt;
+            cmd.raw_arg("/S").raw_arg("/C").raw_arg(format!("\"{}\"", command));
+        }
+        #[cfg(not(target_os = "windows"))]
+        ShellKind::Cmd => {
+            cmd.args(["/C", command]);
+        }
+        // UTF-16LE base64 sidesteps PowerShell's own command-line quote handling
+        ShellKind::PowerShell => {
+            let utf16: Vec<u8> = command.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+            cmd.args(["-NoProfile", "-EncodedCommand", &base64::engine::general_purpose::STANDARD.encode(utf16)]);
+        }
+        ShellKind::Posix => {
+            cmd.args(["-c", command]);
+        }
+    }
+    cmd
+}
+
+#[tauri::command]
+fn set_default_shell(path: String, config: tauri::State<'_, ShellConfig>) -> String {
+    let mut shell = config.default_shell.lock().unwrap();
+    if path.trim().is_empty() {
+        *shell = None;
+        "Default shell reset to platform default".to_string()
+    } else {
+        *shell = Some(path.clone());
+        format!("Default shell set to: {}", path)
+    }
+}
+
+// max_output_bytes caps stdout and stderr each; a command that goes past it
+// is killed and its output ends with a [truncated] marker
+#[tauri::command]
+fn run_shell_command(
+    command: String,
+    max_output_bytes: Option<usize>,
+    config: tauri::State<'_, ShellConfig>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<String, String> {
+    let mut cmd = shell_command(&config, &command);
+    let output = match max_output_bytes {
+        Some(max_bytes) => output_capped(&mut cmd, max_bytes, &automation),
+        None => automation.output(&mut cmd),
+    }
+    .map_err(|e| e.to_string())?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        Err(stderr)
+    }
+}
+
+// Command::output, but keeping at most max_bytes of each stream. Once either
+// overflows the process is killed, and its pipes are closed so anything it
+// started that still holds them gets a broken pipe too. The child is tracked
+// like Automation::output, so cancel_all can kill it.
+fn output_capped(command: &mut Command, max_bytes: usize, automation: &Automation) -> std::io::Result<std::process::Output> {
+    let mut child = command
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    let pid = child.id();
+    automation.children.lock().unwrap().insert(pid);
+
+    let (overflow, overflowed) = std::sync::mpsc::channel();
+    let stdout = read_capped(child.stdout.take(), max_bytes, overflow.clone());
+    let stderr = read_capped(child.stderr.take(), max_bytes, overflow);
+
+    // Ends with an overflow, or with an error once both readers are done
+    if overflowed.recv().is_ok() {
+        child.kill().ok();
+    }
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    let status = child.wait();
+    automation.children.lock().unwrap().remove(&pid);
+    Ok(std::process::Output { status: status?, stdout, stderr })
+}
+
+fn read_capped<R: std::io::Read + Send + 'static>(
+    stream: Option<R>,
+    max_bytes: usize,
+    overflow: std::sync::mpsc::Sender<()>,
+) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut kept = Vec::new();
+        let Some(mut stream) = stream else { return kept };
+        let mut chunk = [0u8; 8192];
+        while let Ok(read) = stream.read(&mut chunk) {
+            if read == 0 {
+                break;
+            }
+            let room = max_bytes - kept.len();
+            kept.extend_from_slice(&chunk[..read.min(room)]);
+            if read > room {
+                kept.extend_from_slice(b"\n[truncated]");
+                overflow.send(()).ok();
+                break;
+            }
+        }
+        kept
+    })
+}
+
+// For CLIs that print JSON (gh, aws, kubectl ...)
+#[tauri::command]
+fn run_shell_command_json(
+    command: String,
+    config: tauri::State<'_, ShellConfig>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<serde_json::Value, String> {
+    let stdout = run_shell_command(command, None, config, automation)?;
+    serde_json::from_str(&stdout)
+        .map_err(|e| format!("Output is not valid JSON ({}): {}", e, stdout))
+}
+
+#[derive(serde::Serialize)]
+struct CommandResult {
+    // None if the command couldn't start or was killed by a signal
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+#[derive(serde::Serialize)]
+struct TimedCommandResult {
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+    // Unix time in milliseconds when the command was started
+    started_at: u64,
+    // Wall-clock run time
+    duration_ms: u64,
+}
+
+// run_shell_command plus timing, for profiling automation steps. A command
+// that fails still returns its result; only a failure to start is an error.
+#[tauri::command(async)]
+fn run_shell_command_timed(
+    command: String,
+    config: tauri::State<'_, ShellConfig>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<TimedCommandResult, String> {
+    let started_at = unix_millis(std::time::SystemTime::now()).unwrap_or(0);
+    let start = std::time::Instant::now();
+    let output = automation.output(&mut shell_command(&config, &command))
         .map_err(|e| e.to_string())?;
 
-    #[cfg(not(target_os = "windows"))]
-    let output = Command::new("sh")
-        .args(["-c", &command])
+    Ok(TimedCommandResult {
+        exit_code: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        started_at,
+        duration_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+// Runs each command in turn through the configured shell, instead of
+// joining them with && (which cmd and sh treat differently)
+#[tauri::command(async)]
+fn run_shell_pipeline(
+    commands: Vec<String>,
+    stop_on_error: bool,
+    config: tauri::State<'_, ShellConfig>,
+    automation: tauri::State<'_, Automation>,
+) -> Vec<CommandResult> {
+    let token = automation.token();
+    let mut results = Vec::new();
+    for command in &commands {
+        // Commands after a cancel never start
+        if automation.check(token).is_err() {
+            break;
+        }
+        let (result, succeeded) = match automation.output(&mut shell_command(&config, command)) {
+            Ok(output) => (
+                CommandResult {
+                    exit_code: output.status.code(),
+                    stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                    stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                },
+                output.status.success(),
+            ),
+            Err(e) => (CommandResult { exit_code: None, stdout: String::new(), stderr: e.to_string() }, false),
+        };
+        results.push(result);
+        if stop_on_error && !succeeded {
+            break;
+        }
+    }
+    results
+}
+
+#[tauri::command]
+fn run_script(language: String, code: String) -> Result<String, String> {
+    let (program, args): (&str, Vec<&str>) = match language.to_lowercase().as_str() {
+        "bash" | "sh" => ("bash", vec!["-c", &code]),
+        #[cfg(target_os = "windows")]
+        "powershell" | "pwsh" => ("powershell", vec!["-NoProfile", "-Command", &code]),
+        #[cfg(not(target_os = "windows"))]
+        "powershell" | "pwsh" => ("pwsh", vec!["-NoProfile", "-Command", &code]),
+        "applescript" => ("osascript", vec!["-e", &code]),
+        "cmd" => ("cmd", vec!["/C", &code]),
+        _ => return Err(format!("Unsupported script language: {}. Use 'bash', 'powershell', 'applescript', or 'cmd'", language)),
+    };
+
+    let output = Command::new(program)
+        .args(&args)
         .output()
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("{} is not available on this system ({} not found)", language, program),
+            _ => e.to_string(),
+        })?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -30,176 +283,5940 @@ fn run_shell_command(command: String) -> Result<String, String> {
     }
 }
 
-#[tauri::command]
-fn get_platform_info() -> PlatformInfo {
-    PlatformInfo {
-        os: std::env::consts::OS.to_string(),
-        arch: std::env::consts::ARCH.to_string(),
-        family: std::env::consts::FAMILY.to_string(),
+// Quotes text as one literal argument for the configured shell, for use in
+// commands passed to run_shell_command
+#[tauri::command]
+fn shell_quote(text: String, config: tauri::State<'_, ShellConfig>) -> String {
+    match configured_shell(&config).1 {
+        // cmd.exe: wrap in double quotes, double any embedded quotes, and step
+        // outside the quotes for % so environment variables are not expanded
+        ShellKind::Cmd => format!("\"{}\"", text.replace('"', "\"\"").replace('%', "\"^%\"")),
+        // PowerShell: single-quoted strings are literal; ' is escaped as ''
+        ShellKind::PowerShell => format!("'{}'", text.replace('\'', "''")),
+        // POSIX sh: single quotes are fully literal, so only ' itself needs care
+        ShellKind::Posix => format!("'{}'", text.replace('\'', "'\\''")),
+    }
+}
+
+#[tauri::command]
+fn get_platform_info() -> PlatformInfo {
+    PlatformInfo {
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        family: std::env::consts::FAMILY.to_string(),
+    }
+}
+
+#[derive(serde::Serialize)]
+struct PlatformInfo {
+    os: String,
+    arch: String,
+    family: String,
+}
+
+// build_date is when the build script last ran, which incremental builds
+// only redo when HEAD moves, so it can be older than the binary
+#[tauri::command]
+fn get_app_info() -> AppInfo {
+    AppInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+        build_date: env!("BUILD_DATE").to_string(),
+        git_commit: env!("GIT_COMMIT").to_string(),
+    }
+}
+
+#[derive(serde::Serialize)]
+struct AppInfo {
+    version: String,
+    tauri_version: String,
+    build_date: String,
+    git_commit: String,
+}
+
+// Environment of the app process, which spawned commands inherit
+#[tauri::command]
+fn get_env(name: String) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+// Variables whose name or value isn't valid Unicode are skipped
+#[tauri::command]
+fn get_all_env() -> std::collections::HashMap<String, String> {
+    std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+// Mutates the app's own process environment, so the variable is inherited by
+// every command spawned afterwards (run_shell_command, run_script, ...) until
+// the app exits. Nothing is persisted to the user's shell profile.
+#[tauri::command]
+fn set_env(name: String, value: String) -> Result<String, String> {
+    // set_var panics on these instead of returning an error
+    if name.is_empty() || name.contains('=') || name.contains('\0') || value.contains('\0') {
+        return Err(format!("Invalid environment variable: {}", name));
+    }
+    std::env::set_var(&name, value);
+    Ok(format!("Set environment variable: {}", name))
+}
+
+// Like set_env this changes the whole app process: commands spawned later
+// (run_shell_command, run_script, ...) start in this directory
+#[tauri::command]
+fn get_cwd() -> Result<String, String> {
+    std::env::current_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_cwd(path: String) -> Result<String, String> {
+    if !std::path::Path::new(&path).is_dir() {
+        return Err(format!("Directory not found: {}", path));
+    }
+    std::env::set_current_dir(&path).map_err(|e| e.to_string())?;
+    get_cwd()
+}
+
+// Release modifiers and mouse buttons in case an interrupted command left them down
+fn release_all_input() {
+    if let Ok(mut enigo) = Enigo::new(&Settings::default()) {
+        for key in [Key::Shift, Key::Control, Key::Alt, Key::Meta] {
+            enigo.key(key, Direction::Release).ok();
+        }
+        for button in [Button::Left, Button::Right, Button::Middle] {
+            enigo.button(button, Direction::Release).ok();
+        }
+    }
+}
+
+#[tauri::command]
+fn restart_app(
+    app: tauri::AppHandle,
+    watch: tauri::State<'_, ClipboardWatch>,
+    mouse: tauri::State<'_, MouseSettings>,
+    automation: tauri::State<'_, Automation>,
+    sessions: tauri::State<'_, PlaywrightSessions>,
+) {
+    // Child processes would otherwise outlive the old instance
+    stop_automation(&automation, &sessions);
+    watch.running.store(false, Ordering::SeqCst);
+    release_all_input();
+    restore_mouse_sensitivity(&mouse);
+    app.restart();
+}
+
+// Input rate limiting
+// Every synthetic event waits for a slot, so loops of input commands can't
+// flood the OS event queue. The default is unlimited (no waiting).
+#[derive(Default)]
+struct InputRateLimiter {
+    state: Mutex<RateLimitState>,
+}
+
+#[derive(Default)]
+struct RateLimitState {
+    interval: Option<std::time::Duration>,
+    last_event: Option<std::time::Instant>,
+}
+
+impl InputRateLimiter {
+    // Blocks until the next event may be sent. Holding the lock while waiting
+    // also serializes input coming from concurrent commands.
+    fn wait(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let (Some(interval), Some(last_event)) = (state.interval, state.last_event) {
+            let elapsed = last_event.elapsed();
+            if elapsed < interval {
+                std::thread::sleep(interval - elapsed);
+            }
+        }
+        state.last_event = Some(std::time::Instant::now());
+    }
+}
+
+// 0 removes the limit
+#[tauri::command]
+fn set_input_rate_limit(events_per_second: u32, limiter: tauri::State<'_, InputRateLimiter>) -> String {
+    let mut state = limiter.state.lock().unwrap();
+    if events_per_second == 0 {
+        state.interval = None;
+        "Input rate limit removed".to_string()
+    } else {
+        state.interval = Some(std::time::Duration::from_secs(1) / events_per_second);
+        format!("Input rate limited to {} events per second", events_per_second)
+    }
+}
+
+// Physical input blocking (BlockInput, Windows only). Only the thread that
+// blocked input may unblock it, so each block runs on its own thread. Input
+// comes back when released, when the watchdog runs out, or when the app
+// exits. Ctrl+Alt+Del always releases it too; app hotkeys can't fire while
+// input is blocked.
+#[derive(Default)]
+struct InputBlock {
+    active: Mutex<Option<(std::sync::mpsc::Sender<()>, std::thread::JoinHandle<()>)>>,
+}
+
+// Waits for the blocking thread so input is back before this returns
+fn release_user_input(block: &InputBlock) {
+    if let Some((release, thread)) = block.active.lock().unwrap().take() {
+        release.send(()).ok();
+        thread.join().ok();
+    }
+}
+
+// timeout_ms is the watchdog: 10s by default, never more than 60s
+#[tauri::command]
+fn block_user_input(enabled: bool, timeout_ms: Option<u64>, block: tauri::State<'_, InputBlock>) -> Result<String, String> {
+    release_user_input(&block);
+    if !enabled {
+        return Ok("User input unblocked".to_string());
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::BlockInput;
+
+        let timeout = std::time::Duration::from_millis(timeout_ms.unwrap_or(10_000).min(60_000));
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let (report, result) = std::sync::mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let blocked = unsafe { BlockInput(1) } != 0;
+            report.send(if blocked { Ok(()) } else { Err(std::io::Error::last_os_error()) }).ok();
+            if blocked {
+                // A release message, the watchdog and a dropped sender all end the block
+                released.recv_timeout(timeout).ok();
+                unsafe { BlockInput(0) };
+            }
+        });
+
+        match result.recv() {
+            Ok(Ok(())) => {
+                *block.active.lock().unwrap() = Some((release, thread));
+                Ok(format!("User input blocked for up to {}ms", timeout.as_millis()))
+            }
+            Ok(Err(e)) => Err(format!("Failed to block input (administrator rights are required): {}", e)),
+            Err(_) => Err("Failed to block input".to_string()),
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = timeout_ms;
+        Err("Blocking user input is only supported on Windows".to_string())
+    }
+}
+
+// Automation cancellation
+// cancel_all bumps the generation; long-running commands take a token when
+// they start and stop at their next check once it's outdated. Child
+// processes started through output(), and Playwright session helpers, are
+// tracked by pid so cancel_all can kill them.
+#[derive(Default)]
+struct Automation {
+    generation: std::sync::atomic::AtomicU64,
+    children: Mutex<std::collections::HashSet<u32>>,
+}
+
+impl Automation {
+    fn token(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    fn check(&self, token: u64) -> Result<(), String> {
+        if self.generation.load(Ordering::SeqCst) == token {
+            Ok(())
+        } else {
+            Err("Cancelled".to_string())
+        }
+    }
+
+    // Sleeps in short slices so a cancel doesn't wait out a long pause
+    fn sleep(&self, token: u64, duration: std::time::Duration) -> Result<(), String> {
+        let deadline = std::time::Instant::now() + duration;
+        loop {
+            self.check(token)?;
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            if remaining.is_zero() {
+                return Ok(());
+            }
+            std::thread::sleep(remaining.min(std::time::Duration::from_millis(50)));
+        }
+    }
+
+    // Command::output, but killable by cancel_all while it runs
+    fn output(&self, command: &mut Command) -> std::io::Result<std::process::Output> {
+        let child = command
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()?;
+        let pid = child.id();
+        self.children.lock().unwrap().insert(pid);
+        let output = child.wait_with_output();
+        self.children.lock().unwrap().remove(&pid);
+        output
+    }
+}
+
+// Outdates every cancel token, kills tracked child processes and closes
+// Playwright sessions. Returns how many processes and sessions went.
+fn stop_automation(automation: &Automation, sessions: &PlaywrightSessions) -> (usize, usize) {
+    automation.generation.fetch_add(1, Ordering::SeqCst);
+
+    // Playwright session helpers are tracked too, so even a session that's
+    // busy in playwright_exec dies here; its step then fails
+    let pids: Vec<sysinfo::Pid> = automation.children.lock().unwrap().drain().map(sysinfo::Pid::from_u32).collect();
+    let mut system = sysinfo::System::new();
+    system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&pids), true);
+    let killed = pids.iter().filter(|pid| system.process(**pid).is_some_and(|process| process.kill())).count();
+    // Dropping the last reference reaps each session
+    let sessions_closed = sessions.sessions.lock().unwrap().drain().count();
+    (killed, sessions_closed)
+}
+
+// The "stop everything" button: ends running sequences and waits, kills
+// tracked child processes and Playwright sessions, stops clipboard watch, key
+// capture, screen recording and bubble hover detection, unblocks input, and
+// releases modifier keys and mouse buttons that are still down
+#[tauri::command(async)]
+fn cancel_all(
+    app: tauri::AppHandle,
+    automation: tauri::State<'_, Automation>,
+    sessions: tauri::State<'_, PlaywrightSessions>,
+    watch: tauri::State<'_, ClipboardWatch>,
+    hook: tauri::State<'_, InputHook>,
+    hover: tauri::State<'_, BubbleHover>,
+    block: tauri::State<'_, InputBlock>,
+) -> Result<String, String> {
+    let (killed, sessions_closed) = stop_automation(&automation, &sessions);
+    release_user_input(&block);
+
+    watch.running.store(false, Ordering::SeqCst);
+    hover.generation.fetch_add(1, Ordering::SeqCst);
+    if hook.state.lock().unwrap().capturing {
+        stop_key_capture(app.clone(), hook);
+    }
+    // The recording so far is kept, finalized so it still plays
+    let recording = app.state::<ScreenRecorder>().active.lock().unwrap().take();
+    if let Some(recording) = recording {
+        finish_screen_recording(recording).ok();
+    }
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    for key in [Key::Shift, Key::Control, Key::Alt, Key::Meta] {
+        enigo.key(key, Direction::Release).ok();
+    }
+    if let Ok(buttons) = get_mouse_button_state() {
+        for (down, button) in [(buttons.left, Button::Left), (buttons.right, Button::Right), (buttons.middle, Button::Middle)] {
+            if down {
+                enigo.button(button, Direction::Release).ok();
+            }
+        }
+    }
+
+    app.emit("automation-cancelled", ()).ok();
+    Ok(format!("Cancelled all automation ({} processes killed, {} Playwright sessions closed)", killed, sessions_closed))
+}
+
+// enigo's own messages ("simulating input failed") don't say how to fix
+// anything, so input commands report failures through this instead
+fn describe_enigo_error(e: impl std::error::Error + 'static) -> String {
+    #[cfg(target_os = "macos")]
+    const PERMISSION_HINT: &str = "grant this app Accessibility access in System Settings > Privacy & Security > Accessibility";
+    #[cfg(target_os = "windows")]
+    const PERMISSION_HINT: &str = "input to apps running as administrator is blocked unless this app is elevated too";
+    #[cfg(target_os = "linux")]
+    const PERMISSION_HINT: &str = "check that an X11 session is running (Wayland compositors may block simulated input)";
+
+    let error: &(dyn std::error::Error + 'static) = &e;
+    if let Some(error) = error.downcast_ref::<enigo::NewConError>() {
+        return match error {
+            enigo::NewConError::EstablishCon(detail) => format!(
+                "Could not connect to the input system ({}). Is a display available? Also {}", detail, PERMISSION_HINT
+            ),
+            enigo::NewConError::Reply => "The display server sent an unexpected reply; try again or restart the session".to_string(),
+            enigo::NewConError::NoEmptyKeycodes => "The keyboard map is full, so no keys can be simulated; free a keycode or restart the session".to_string(),
+        };
+    }
+    if let Some(error) = error.downcast_ref::<enigo::InputError>() {
+        return match error {
+            enigo::InputError::Simulate(detail) => format!("The system rejected the simulated input ({}); {}", detail, PERMISSION_HINT),
+            enigo::InputError::Mapping(detail) => format!("This key can't be typed with the current keyboard layout ({}); try another key name", detail),
+            enigo::InputError::Unmapping(detail) => format!("Could not release a temporary key mapping ({})", detail),
+            enigo::InputError::NoEmptyKeycodes => "No free keycode to map this key to; try fewer distinct special characters at once".to_string(),
+            enigo::InputError::InvalidInput(detail) => format!("Unsupported input ({}); text must not contain NUL characters", detail),
+        };
+    }
+    e.to_string()
+}
+
+// Mouse control commands
+
+// Whether a point lies on any display. Checks each display's own bounds, so
+// gaps between monitors of different sizes count as off-screen.
+#[tauri::command]
+fn is_point_on_screen(app: tauri::AppHandle, x: i32, y: i32) -> Result<bool, String> {
+    Ok(display_layout(&app)?.iter().any(|display| {
+        x >= display.x
+            && y >= display.y
+            && (x as i64) < display.x as i64 + display.width as i64
+            && (y as i64) < display.y as i64 + display.height as i64
+    }))
+}
+
+#[tauri::command]
+fn mouse_move(x: i32, y: i32, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.move_mouse(x, y, Coordinate::Abs).map_err(describe_enigo_error)?;
+    Ok(format!("Moved mouse to ({}, {})", x, y))
+}
+
+// Hot corners: the exact corner pixel of the display the cursor is on
+#[tauri::command]
+fn mouse_move_to_corner(app: tauri::AppHandle, corner: String, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let cursor = app.cursor_position().map_err(|e| e.to_string())?;
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    let monitor = monitors
+        .iter()
+        .find(|monitor| monitor_contains(monitor, cursor.x as i32, cursor.y as i32))
+        .ok_or("Cursor is not on any display")?;
+
+    let position = monitor.position();
+    let size = monitor.size();
+    let right = position.x + size.width as i32 - 1;
+    let bottom = position.y + size.height as i32 - 1;
+    let (x, y) = match corner.as_str() {
+        "top-left" => (position.x, position.y),
+        "top-right" => (right, position.y),
+        "bottom-left" => (position.x, bottom),
+        "bottom-right" => (right, bottom),
+        _ => return Err(format!("Invalid corner: {}. Use 'top-left', 'top-right', 'bottom-left', or 'bottom-right'", corner)),
+    };
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.move_mouse(x, y, Coordinate::Abs).map_err(describe_enigo_error)?;
+    Ok(format!("Moved mouse to {} corner ({}, {})", corner, x, y))
+}
+
+// Pinch zoom around a point; scale > 1 zooms in, < 1 zooms out.
+// - Windows injects a real two-finger touch pinch (InjectTouchInput), which
+//   touch-aware apps like Maps and Photos treat as a gesture.
+// - Elsewhere, and on Windows if touch injection is unavailable, it's
+//   emulated with Ctrl+scroll, one notch per ~10% of zoom. Browsers, maps and
+//   most image viewers zoom on that. On macOS, Ctrl+scroll zooms the whole
+//   screen instead if that accessibility option is turned on.
+#[tauri::command(async)]
+fn gesture_zoom(center_x: i32, center_y: i32, scale: f64, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(format!("Invalid zoom scale: {}", scale));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::{POINT, RECT};
+        use windows_sys::Win32::UI::Input::Pointer::{
+            InitializeTouchInjection, InjectTouchInput, POINTER_FLAG_DOWN, POINTER_FLAG_INCONTACT, POINTER_FLAG_INRANGE,
+            POINTER_FLAG_UP, POINTER_FLAG_UPDATE, POINTER_TOUCH_INFO, TOUCH_FEEDBACK_DEFAULT,
+        };
+        use windows_sys::Win32::UI::WindowsAndMessaging::{PT_TOUCH, TOUCH_MASK_CONTACTAREA, TOUCH_MASK_ORIENTATION, TOUCH_MASK_PRESSURE};
+
+        // Two fingers side by side, each `spread` px from the centre
+        let contact = |id: u32, x: i32, y: i32, flags: u32| unsafe {
+            let mut info: POINTER_TOUCH_INFO = std::mem::zeroed();
+            info.pointerInfo.pointerType = PT_TOUCH;
+            info.pointerInfo.pointerId = id;
+            info.pointerInfo.pointerFlags = flags;
+            info.pointerInfo.ptPixelLocation = POINT { x, y };
+            info.touchMask = TOUCH_MASK_CONTACTAREA | TOUCH_MASK_ORIENTATION | TOUCH_MASK_PRESSURE;
+            info.rcContact = RECT { left: x - 2, top: y - 2, right: x + 2, bottom: y + 2 };
+            info.orientation = 90;
+            info.pressure = 32000;
+            info
+        };
+        let frame = |spread: f64, flags: u32| {
+            let spread = spread.round() as i32;
+            let contacts = [
+                contact(0, center_x - spread, center_y, flags),
+                contact(1, center_x + spread, center_y, flags),
+            ];
+            unsafe { InjectTouchInput(2, contacts.as_ptr()) != 0 }
+        };
+
+        if unsafe { InitializeTouchInjection(2, TOUCH_FEEDBACK_DEFAULT) } != 0 {
+            const STEPS: u32 = 10;
+            let (from, to) = if scale >= 1.0 { (50.0, 50.0 * scale) } else { (50.0 / scale, 50.0) };
+            let (from, to) = (from.min(400.0), to.min(400.0));
+
+            limiter.wait();
+            let mut ok = frame(from, POINTER_FLAG_DOWN | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT);
+            for step in 1..=STEPS {
+                if !ok {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                let spread = from + (to - from) * step as f64 / STEPS as f64;
+                ok = frame(spread, POINTER_FLAG_UPDATE | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT);
+            }
+            // Always lift the fingers, even after a failed frame
+            frame(to, POINTER_FLAG_UP);
+            if ok {
+                return Ok(format!("Pinch zoomed x{} at ({}, {})", scale, center_x, center_y));
+            }
+        }
+    }
+
+    // At least one notch for any scale other than 1
+    let notches = match (scale.ln() / 1.1f64.ln()).round() as i32 {
+        0 if scale > 1.0 => 1,
+        0 if scale < 1.0 => -1,
+        notches => notches,
+    };
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.move_mouse(center_x, center_y, Coordinate::Abs).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.key(Key::Control, Direction::Press).map_err(describe_enigo_error)?;
+    let mut scrolled = Ok(());
+    for _ in 0..notches.abs() {
+        limiter.wait();
+        // Scrolling up (negative) zooms in
+        scrolled = enigo.scroll(-notches.signum(), enigo::Axis::Vertical);
+        if scrolled.is_err() {
+            break;
+        }
+    }
+    limiter.wait();
+    enigo.key(Key::Control, Direction::Release).map_err(describe_enigo_error)?;
+    scrolled.map_err(describe_enigo_error)?;
+    Ok(format!("Zoomed x{} at ({}, {}) with {} Ctrl+scroll steps", scale, center_x, center_y, notches.abs()))
+}
+
+fn parse_button(button: &str) -> Result<Button, String> {
+    match button.to_lowercase().as_str() {
+        "left" => Ok(Button::Left),
+        "right" => Ok(Button::Right),
+        "middle" => Ok(Button::Middle),
+        // Extra side buttons, used by browsers for history navigation
+        #[cfg(not(target_os = "macos"))]
+        "back" | "x1" => Ok(Button::Back),
+        #[cfg(not(target_os = "macos"))]
+        "forward" | "x2" => Ok(Button::Forward),
+        #[cfg(target_os = "macos")]
+        "back" | "x1" | "forward" | "x2" => Err(format!("Button {} is not supported on macOS", button)),
+        _ => Err(format!("Invalid button: {}. Use 'left', 'right', 'middle', 'back', or 'forward'", button)),
+    }
+}
+
+#[tauri::command]
+fn mouse_click(button: String, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    let mouse_button = parse_button(&button)?;
+    limiter.wait();
+    enigo.button(mouse_button, Direction::Click).map_err(describe_enigo_error)?;
+    Ok(format!("Clicked {} mouse button", button))
+}
+
+#[tauri::command]
+fn mouse_scroll(amount: i32, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    // Positive = scroll down, negative = scroll up
+    enigo.scroll(amount, enigo::Axis::Vertical).map_err(describe_enigo_error)?;
+    Ok(format!("Scrolled mouse by {}", amount))
+}
+
+// Positive dy scrolls down and positive dx scrolls right, like mouse_scroll.
+// Granularity depends on the platform:
+// - macOS posts true pixel scroll events.
+// - Windows sends sub-notch wheel deltas (a 120 unit notch is treated as
+//   60 px); apps without smooth scrolling only move once a notch builds up.
+// - Linux (X11 and Wayland) only has whole line scrolls, so the distance is
+//   rounded to lines of about 20 px, with at least one line per moving axis.
+#[tauri::command]
+fn mouse_scroll_pixels(dx: i32, dy: i32, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    limiter.wait();
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{SendInput, INPUT, INPUT_0, INPUT_MOUSE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_WHEEL, MOUSEINPUT};
+
+        let wheel = |flags, delta: i32| INPUT {
+            r#type: INPUT_MOUSE,
+            Anonymous: INPUT_0 {
+                mi: MOUSEINPUT { dx: 0, dy: 0, mouseData: delta as u32, dwFlags: flags, time: 0, dwExtraInfo: 0 },
+            },
+        };
+        // WHEEL is positive upwards, HWHEEL positive to the right
+        let mut inputs = Vec::new();
+        if dy != 0 {
+            inputs.push(wheel(MOUSEEVENTF_WHEEL, dy.saturating_mul(-2)));
+        }
+        if dx != 0 {
+            inputs.push(wheel(MOUSEEVENTF_HWHEEL, dx.saturating_mul(2)));
+        }
+        let sent = unsafe { SendInput(inputs.len() as u32, inputs.as_ptr(), std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            return Err("Failed to send scroll input".to_string());
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use core_graphics::event::{CGEvent, CGEventTapLocation, ScrollEventUnit};
+        use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+
+        let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+            .map_err(|_| "Failed to create event source".to_string())?;
+        // Wheel 1 is vertical (positive up), wheel 2 horizontal (positive left)
+        let event = CGEvent::new_scroll_event(source, ScrollEventUnit::PIXEL, 2, dy.saturating_neg(), dx.saturating_neg(), 0)
+            .map_err(|_| "Failed to create scroll event".to_string())?;
+        event.post(CGEventTapLocation::HID);
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        const PIXELS_PER_LINE: f64 = 20.0;
+        let lines = |pixels: i32| match (pixels as f64 / PIXELS_PER_LINE).round() as i32 {
+            0 => pixels.signum(),
+            n => n,
+        };
+
+        let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+        if dy != 0 {
+            enigo.scroll(lines(dy), enigo::Axis::Vertical).map_err(describe_enigo_error)?;
+        }
+        if dx != 0 {
+            enigo.scroll(lines(dx), enigo::Axis::Horizontal).map_err(describe_enigo_error)?;
+        }
+    }
+
+    Ok(format!("Scrolled mouse by ({}, {}) px", dx, dy))
+}
+
+#[tauri::command]
+fn get_mouse_position() -> Result<(i32, i32), String> {
+    let enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    let (x, y) = enigo.location().map_err(describe_enigo_error)?;
+    Ok((x, y))
+}
+
+#[derive(serde::Serialize)]
+struct MouseButtonState {
+    left: bool,
+    right: bool,
+    middle: bool,
+}
+
+// Reads the OS-level button state, so it reflects the user's physical mouse
+// as well as any synthetic presses still held down by our own commands
+#[tauri::command]
+fn get_mouse_button_state() -> Result<MouseButtonState, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_LBUTTON, VK_MBUTTON, VK_RBUTTON};
+        use windows_sys::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_SWAPBUTTON};
+
+        let down = |vk: u16| unsafe { GetAsyncKeyState(vk as i32) } as u16 & 0x8000 != 0;
+        // GetAsyncKeyState reports physical buttons; swap them back for left-handed setups
+        let (primary, secondary) = if unsafe { GetSystemMetrics(SM_SWAPBUTTON) } != 0 {
+            (VK_RBUTTON, VK_LBUTTON)
+        } else {
+            (VK_LBUTTON, VK_RBUTTON)
+        };
+        Ok(MouseButtonState { left: down(primary), right: down(secondary), middle: down(VK_MBUTTON) })
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        #[link(name = "CoreGraphics", kind = "framework")]
+        extern "C" {
+            fn CGEventSourceButtonState(state_id: i32, button: u32) -> bool;
+        }
+        const HID_SYSTEM_STATE: i32 = 1;
+
+        let down = |button: u32| unsafe { CGEventSourceButtonState(HID_SYSTEM_STATE, button) };
+        Ok(MouseButtonState { left: down(0), right: down(1), middle: down(2) })
+    }
+
+    // X11 only: xinput prints lines like "button[1]=down"
+    #[cfg(target_os = "linux")]
+    {
+        let output = Command::new("xinput")
+            .args(["--query-state", "Virtual core pointer"])
+            .output()
+            .map_err(|e| format!("Failed to run xinput (is it installed?): {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let down = |button: u32| stdout.contains(&format!("button[{}]=down", button));
+        Ok(MouseButtonState { left: down(1), right: down(3), middle: down(2) })
+    }
+}
+
+// OS pointer speed (1-20, 10 is the default) and acceleration ("Enhance
+// pointer precision"). Relative moves only land predictably with acceleration
+// off. Windows only; changes last for the session and the original settings
+// are put back when the app exits.
+#[derive(Clone, Copy, serde::Serialize)]
+struct MouseSensitivity {
+    speed: u32,
+    acceleration: bool,
+}
+
+// The user's settings from before the first change, restored on exit
+#[derive(Default)]
+struct MouseSettings {
+    original: Mutex<Option<MouseSensitivity>>,
+}
+
+fn read_mouse_sensitivity() -> Result<MouseSensitivity, String> {
+    #[cfg(target_os = "windows")]
+    unsafe {
+        use windows_sys::Win32::UI::WindowsAndMessaging::{SystemParametersInfoW, SPI_GETMOUSE, SPI_GETMOUSESPEED};
+
+        let mut speed = 0u32;
+        // Two acceleration thresholds, then the acceleration flag
+        let mut mouse = [0i32; 3];
+        if SystemParametersInfoW(SPI_GETMOUSESPEED, 0, &mut speed as *mut u32 as *mut _, 0) == 0
+            || SystemParametersInfoW(SPI_GETMOUSE, 0, mouse.as_mut_ptr() as *mut _, 0) == 0
+        {
+            return Err(format!("Failed to read mouse settings: {}", std::io::Error::last_os_error()));
+        }
+        Ok(MouseSensitivity { speed, acceleration: mouse[2] != 0 })
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    Err("Mouse sensitivity is only supported on Windows".to_string())
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(unused_variables))]
+fn write_mouse_sensitivity(settings: MouseSensitivity) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    unsafe {
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            SystemParametersInfoW, SPIF_SENDCHANGE, SPI_GETMOUSE, SPI_SETMOUSE, SPI_SETMOUSESPEED,
+        };
+
+        let mut mouse = [0i32; 3];
+        SystemParametersInfoW(SPI_GETMOUSE, 0, mouse.as_mut_ptr() as *mut _, 0);
+        mouse[2] = settings.acceleration as i32;
+        // No SPIF_UPDATEINIFILE: the change is never saved to the user's profile
+        if SystemParametersInfoW(SPI_SETMOUSESPEED, 0, settings.speed as usize as *mut _, SPIF_SENDCHANGE) == 0
+            || SystemParametersInfoW(SPI_SETMOUSE, 0, mouse.as_mut_ptr() as *mut _, SPIF_SENDCHANGE) == 0
+        {
+            return Err(format!("Failed to change mouse settings: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    Err("Mouse sensitivity is only supported on Windows".to_string())
+}
+
+fn restore_mouse_sensitivity(settings: &MouseSettings) {
+    if let Some(original) = settings.original.lock().unwrap().take() {
+        write_mouse_sensitivity(original).ok();
+    }
+}
+
+#[tauri::command]
+fn get_mouse_sensitivity() -> Result<MouseSensitivity, String> {
+    read_mouse_sensitivity()
+}
+
+// acceleration is left as it is when None
+#[tauri::command]
+fn set_mouse_sensitivity(level: u32, acceleration: Option<bool>, settings: tauri::State<'_, MouseSettings>) -> Result<MouseSensitivity, String> {
+    if !(1..=20).contains(&level) {
+        return Err(format!("Invalid mouse speed: {}. Use 1-20", level));
+    }
+
+    let current = read_mouse_sensitivity()?;
+    settings.original.lock().unwrap().get_or_insert(current);
+
+    let applied = MouseSensitivity { speed: level, acceleration: acceleration.unwrap_or(current.acceleration) };
+    write_mouse_sensitivity(applied)?;
+    Ok(applied)
+}
+
+// Keyboard control commands
+// Sends the whole string in one go; see keyboard_type_raw if an app drops characters
+#[tauri::command]
+fn keyboard_type(text: String, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.text(&text).map_err(describe_enigo_error)?;
+    Ok(format!("Typed: {}", text))
+}
+
+// Sends each character as its own key click instead of one bulk text event.
+// Slower than keyboard_type, but apps whose autocomplete swallows fast
+// bulk input tend to handle discrete key events predictably.
+#[tauri::command(async)]
+fn keyboard_type_raw(
+    text: String,
+    limiter: tauri::State<'_, InputRateLimiter>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<String, String> {
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    type_chars(&mut enigo, &text, &limiter, &automation, automation.token())?;
+    Ok(format!("Typed: {}", text))
+}
+
+fn type_chars(enigo: &mut Enigo, text: &str, limiter: &InputRateLimiter, automation: &Automation, token: u64) -> Result<(), String> {
+    for c in text.chars() {
+        automation.check(token)?;
+        let key = match c {
+            '\n' => Key::Return,
+            '\t' => Key::Tab,
+            '\r' => continue,
+            _ => Key::Unicode(c),
+        };
+        limiter.wait();
+        enigo.key(key, Direction::Click).map_err(describe_enigo_error)?;
+    }
+    Ok(())
+}
+
+#[tauri::command(async)]
+fn type_at(
+    x: i32,
+    y: i32,
+    text: String,
+    settle_ms: Option<u64>,
+    limiter: tauri::State<'_, InputRateLimiter>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<String, String> {
+    let token = automation.token();
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.move_mouse(x, y, Coordinate::Abs).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.button(Button::Left, Direction::Click).map_err(describe_enigo_error)?;
+
+    // Give the target field time to take focus before typing starts
+    automation.sleep(token, std::time::Duration::from_millis(settle_ms.unwrap_or(100)))?;
+
+    limiter.wait();
+    enigo.text(&text).map_err(describe_enigo_error)?;
+    Ok(format!("Typed at ({}, {}): {}", x, y, text))
+}
+
+// Gives the user time to click into the target field, since triggering this
+// from our own window takes focus away from it. cancel_all stops the wait.
+#[tauri::command(async)]
+fn keyboard_type_delayed(
+    text: String,
+    delay_ms: u64,
+    limiter: tauri::State<'_, InputRateLimiter>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<String, String> {
+    automation.sleep(automation.token(), std::time::Duration::from_millis(delay_ms))?;
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.text(&text).map_err(describe_enigo_error)?;
+    Ok(format!("Typed after {}ms: {}", delay_ms, text))
+}
+
+// Typing diagnostics: test_typing opens typing-test.html, types into its
+// focused field and compares what arrived. The page reports None once it's
+// loaded and focused, then the field's contents when asked.
+#[derive(Default)]
+struct TypingTest {
+    sender: Mutex<Option<std::sync::mpsc::Sender<Option<String>>>>,
+}
+
+#[derive(serde::Serialize)]
+struct TypingTestResult {
+    matched: bool,
+    got: String,
+    expected: String,
+}
+
+// raw: type key by key like keyboard_type_raw instead of in one go like
+// keyboard_type. Run it with the input rate limit under test.
+#[tauri::command(async)]
+fn test_typing(
+    app: tauri::AppHandle,
+    text: String,
+    raw: Option<bool>,
+    test: tauri::State<'_, TypingTest>,
+    limiter: tauri::State<'_, InputRateLimiter>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<TypingTestResult, String> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    *test.sender.lock().unwrap() = Some(sender);
+
+    let window = tauri::WebviewWindowBuilder::new(&app, "typing-test", tauri::WebviewUrl::App("typing-test.html".into()))
+        .title("PAL Typing Test")
+        .inner_size(360.0, 120.0)
+        .center()
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(false)
+        .focused(true)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let token = automation.token();
+    let result = (|| {
+        let timeout = std::time::Duration::from_secs(5);
+        receiver.recv_timeout(timeout).map_err(|_| "Typing test window didn't load".to_string())?;
+        window.set_focus().ok();
+
+        let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+        if raw.unwrap_or(false) {
+            type_chars(&mut enigo, &text, &limiter, &automation, token)?;
+        } else {
+            limiter.wait();
+            enigo.text(&text).map_err(describe_enigo_error)?;
+        }
+
+        // Let the last key events reach the field before reading it
+        automation.sleep(token, std::time::Duration::from_millis(300))?;
+        window.eval("window.palReportTypingTest()").map_err(|e| e.to_string())?;
+        receiver.recv_timeout(timeout).ok().flatten().ok_or("Typing test window didn't report".to_string())
+    })();
+
+    test.sender.lock().unwrap().take();
+    window.close().ok();
+
+    let got = result?;
+    // Text fields store line breaks as \n
+    let expected = text.replace("\r\n", "\n").replace('\r', "");
+    Ok(TypingTestResult { matched: got == expected, got, expected })
+}
+
+// Called by typing-test.html
+#[tauri::command]
+fn typing_test_report(value: Option<String>, test: tauri::State<'_, TypingTest>) {
+    if let Some(sender) = test.sender.lock().unwrap().as_ref() {
+        sender.send(value).ok();
+    }
+}
+
+#[tauri::command]
+fn keyboard_press(key: String, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    let enigo_key = parse_key(&key)?;
+    limiter.wait();
+    enigo.key(enigo_key, Direction::Click).map_err(describe_enigo_error)?;
+    Ok(format!("Pressed key: {}", key))
+}
+
+// Hold a key for a while, for apps (mostly games) that ignore instant clicks
+#[tauri::command(async)]
+fn keyboard_tap(
+    key: String,
+    hold_ms: u64,
+    limiter: tauri::State<'_, InputRateLimiter>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<String, String> {
+    let token = automation.token();
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    let enigo_key = parse_key_or_char(&key)?;
+    limiter.wait();
+    enigo.key(enigo_key, Direction::Press).map_err(describe_enigo_error)?;
+    let held = automation.sleep(token, std::time::Duration::from_millis(hold_ms));
+    limiter.wait();
+    enigo.key(enigo_key, Direction::Release).map_err(describe_enigo_error)?;
+    held?;
+    Ok(format!("Held key {} for {}ms", key, hold_ms))
+}
+
+#[tauri::command]
+fn media_key(action: String, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+
+    let enigo_key = match action.to_lowercase().as_str() {
+        "play_pause" => Key::MediaPlayPause,
+        "next" => Key::MediaNextTrack,
+        "previous" => Key::MediaPrevTrack,
+        "volume_up" => Key::VolumeUp,
+        "volume_down" => Key::VolumeDown,
+        "mute" => Key::VolumeMute,
+        #[cfg(not(target_os = "macos"))]
+        "stop" => Key::MediaStop,
+        #[cfg(target_os = "macos")]
+        "stop" => return Err("Media stop key is not supported on macOS".to_string()),
+        _ => return Err(format!("Unsupported media action: {}. Use 'play_pause', 'next', 'previous', 'volume_up', 'volume_down', 'mute', or 'stop'", action)),
+    };
+
+    limiter.wait();
+    enigo.key(enigo_key, Direction::Click).map_err(describe_enigo_error)?;
+    Ok(format!("Sent media key: {}", action))
+}
+
+fn parse_key(key: &str) -> Result<Key, String> {
+    let enigo_key = match key.to_lowercase().as_str() {
+        "enter" | "return" => Key::Return,
+        "tab" => Key::Tab,
+        "escape" | "esc" => Key::Escape,
+        "backspace" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "up" | "uparrow" => Key::UpArrow,
+        "down" | "downarrow" => Key::DownArrow,
+        "left" | "leftarrow" => Key::LeftArrow,
+        "right" | "rightarrow" => Key::RightArrow,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "space" => Key::Space,
+        "shift" => Key::Shift,
+        "control" | "ctrl" => Key::Control,
+        "alt" => Key::Alt,
+        "meta" | "windows" | "cmd" | "command" => Key::Meta,
+        _ => return Err(format!("Unsupported key: {}. Use special key names like 'enter', 'tab', 'escape', etc.", key)),
+    };
+    Ok(enigo_key)
+}
+
+// Named keys plus single characters, for commands that hold individual keys
+fn parse_key_or_char(key: &str) -> Result<Key, String> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(Key::Unicode(c)),
+        _ => parse_key(key),
+    }
+}
+
+#[derive(serde::Deserialize)]
+enum KeyStep {
+    Type(String),
+    Press(String),
+    Down(String),
+    Up(String),
+    Wait(u64),
+}
+
+#[tauri::command(async)]
+fn keyboard_sequence(
+    steps: Vec<KeyStep>,
+    limiter: tauri::State<'_, InputRateLimiter>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<String, String> {
+    let token = automation.token();
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    let mut held: Vec<Key> = Vec::new();
+
+    let run = |enigo: &mut Enigo, held: &mut Vec<Key>| -> Result<(), String> {
+        for step in &steps {
+            automation.check(token)?;
+            if !matches!(step, KeyStep::Wait(_)) {
+                limiter.wait();
+            }
+            match step {
+                KeyStep::Type(text) => enigo.text(text).map_err(describe_enigo_error)?,
+                KeyStep::Press(key) => enigo.key(parse_key_or_char(key)?, Direction::Click).map_err(describe_enigo_error)?,
+                KeyStep::Down(key) => {
+                    let key = parse_key_or_char(key)?;
+                    enigo.key(key, Direction::Press).map_err(describe_enigo_error)?;
+                    held.push(key);
+                }
+                KeyStep::Up(key) => {
+                    let key = parse_key_or_char(key)?;
+                    enigo.key(key, Direction::Release).map_err(describe_enigo_error)?;
+                    held.retain(|k| *k != key);
+                }
+                KeyStep::Wait(ms) => automation.sleep(token, std::time::Duration::from_millis(*ms))?,
+            }
+        }
+        Ok(())
+    };
+
+    if let Err(e) = run(&mut enigo, &mut held) {
+        // Never leave modifiers stuck down after a failed step
+        for key in held.iter().rev() {
+            enigo.key(*key, Direction::Release).ok();
+        }
+        return Err(e);
+    }
+
+    Ok(format!("Executed {} key steps", steps.len()))
+}
+
+#[tauri::command]
+fn keyboard_shortcut(keys: Vec<String>, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    press_shortcut(&mut enigo, &keys, &limiter)?;
+    Ok(format!("Executed keyboard shortcut: {}", keys.join("+")))
+}
+
+fn press_shortcut(enigo: &mut Enigo, keys: &[String], limiter: &InputRateLimiter) -> Result<(), String> {
+    // Press all keys down
+    for key in keys {
+        limiter.wait();
+        let enigo_key = match key.to_lowercase().as_str() {
+            "shift" => Key::Shift,
+            "control" | "ctrl" => Key::Control,
+            "alt" => Key::Alt,
+            "meta" | "windows" | "cmd" | "command" => Key::Meta,
+            single if single.len() == 1 => {
+                enigo.text(single).map_err(describe_enigo_error)?;
+                continue;
+            }
+            _ => return Err(format!("Unsupported key in shortcut: {}", key)),
+        };
+        enigo.key(enigo_key, Direction::Press).map_err(describe_enigo_error)?;
+    }
+
+    // Release all keys
+    for key in keys.iter().rev() {
+        let enigo_key = match key.to_lowercase().as_str() {
+            "shift" => Key::Shift,
+            "control" | "ctrl" => Key::Control,
+            "alt" => Key::Alt,
+            "meta" | "windows" | "cmd" | "command" => Key::Meta,
+            _ => continue,
+        };
+        limiter.wait();
+        enigo.key(enigo_key, Direction::Release).map_err(describe_enigo_error)?;
+    }
+
+    Ok(())
+}
+
+// Lock key state, so automations can make sure Caps Lock is off before typing
+#[derive(serde::Serialize)]
+struct LockKeys {
+    caps: bool,
+    num: bool,
+    scroll: bool,
+}
+
+#[tauri::command]
+fn get_lock_keys() -> Result<LockKeys, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetKeyState, VK_CAPITAL, VK_NUMLOCK, VK_SCROLL};
+
+        // The low bit of GetKeyState is the toggle state
+        let toggled = |vk: u16| unsafe { GetKeyState(vk as i32) } & 1 != 0;
+        Ok(LockKeys { caps: toggled(VK_CAPITAL), num: toggled(VK_NUMLOCK), scroll: toggled(VK_SCROLL) })
+    }
+
+    // Macs have no Num Lock or Scroll Lock, so those are always false
+    #[cfg(target_os = "macos")]
+    {
+        #[link(name = "CoreGraphics", kind = "framework")]
+        extern "C" {
+            fn CGEventSourceFlagsState(state_id: i32) -> u64;
+        }
+        const HID_SYSTEM_STATE: i32 = 1;
+        const ALPHA_SHIFT: u64 = 0x0001_0000;
+
+        let flags = unsafe { CGEventSourceFlagsState(HID_SYSTEM_STATE) };
+        Ok(LockKeys { caps: flags & ALPHA_SHIFT != 0, num: false, scroll: false })
+    }
+
+    // xset q: "00: Caps Lock:   off    01: Num Lock:    on     02: Scroll Lock: off"
+    #[cfg(target_os = "linux")]
+    {
+        let output = Command::new("xset")
+            .arg("q")
+            .output()
+            .map_err(|e| format!("Failed to run xset (is it installed?): {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let state = |name: &str| {
+            stdout.split(name).nth(1)
+                .and_then(|rest| rest.split_whitespace().next())
+                .map(|value| value == "on")
+                .ok_or(format!("xset did not report {}", name.trim_end_matches(':')))
+        };
+        Ok(LockKeys { caps: state("Caps Lock:")?, num: state("Num Lock:")?, scroll: state("Scroll Lock:")? })
+    }
+}
+
+// key: "caps", "num" or "scroll". Toggles the key only if it's in the wrong
+// state, then checks that the OS actually picked up the change.
+#[tauri::command(async)]
+fn set_lock_key(key: String, enabled: bool, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let current = |keys: &LockKeys| match key.to_lowercase().as_str() {
+        "caps" | "capslock" => Ok(keys.caps),
+        "num" | "numlock" => Ok(keys.num),
+        "scroll" | "scrolllock" => Ok(keys.scroll),
+        _ => Err(format!("Unsupported lock key: {}. Use 'caps', 'num', or 'scroll'", key)),
+    };
+    if current(&get_lock_keys()?)? == enabled {
+        return Ok(format!("{} lock already {}", key, if enabled { "on" } else { "off" }));
+    }
+
+    let lock_key = match key.to_lowercase().as_str() {
+        "caps" | "capslock" => Key::CapsLock,
+        #[cfg(not(target_os = "macos"))]
+        "num" | "numlock" => Key::Numlock,
+        #[cfg(target_os = "windows")]
+        "scroll" | "scrolllock" => Key::Scroll,
+        #[cfg(target_os = "linux")]
+        "scroll" | "scrolllock" => Key::ScrollLock,
+        _ => return Err(format!("{} lock is not available on this platform", key)),
+    };
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.key(lock_key, Direction::Click).map_err(describe_enigo_error)?;
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    if current(&get_lock_keys()?)? != enabled {
+        return Err(format!("{} lock did not change; the OS may ignore synthetic lock key presses", key));
+    }
+    Ok(format!("{} lock turned {}", key, if enabled { "on" } else { "off" }))
+}
+
+// Window management commands
+// Repeated toggle_main_window calls within `window` of the last accepted one
+// are ignored, so a double-triggered hotkey doesn't flicker the window
+struct ToggleDebounce {
+    state: Mutex<ToggleDebounceState>,
+}
+
+struct ToggleDebounceState {
+    window: std::time::Duration,
+    last_toggle: Option<std::time::Instant>,
+}
+
+impl Default for ToggleDebounce {
+    fn default() -> Self {
+        ToggleDebounce {
+            state: Mutex::new(ToggleDebounceState {
+                window: std::time::Duration::from_millis(200),
+                last_toggle: None,
+            }),
+        }
+    }
+}
+
+// 0 disables debouncing
+#[tauri::command]
+fn set_toggle_debounce_ms(ms: u64, debounce: tauri::State<'_, ToggleDebounce>) -> String {
+    debounce.state.lock().unwrap().window = std::time::Duration::from_millis(ms);
+    format!("Window toggle debounce set to {}ms", ms)
+}
+
+#[tauri::command]
+fn toggle_main_window(app: tauri::AppHandle, debounce: tauri::State<'_, ToggleDebounce>) -> Result<String, String> {
+    // Held for the whole toggle so concurrent calls can't interleave show/hide
+    let mut state = debounce.state.lock().unwrap();
+    if state.last_toggle.is_some_and(|last| last.elapsed() < state.window) {
+        return Ok("Toggle ignored (debounced)".to_string());
+    }
+    state.last_toggle = Some(std::time::Instant::now());
+
+    let main_window = app.get_webview_window("main")
+        .ok_or("Main window not found")?;
+    let bubble_window = app.get_webview_window("bubble")
+        .ok_or("Bubble window not found")?;
+
+    if main_window.is_visible().unwrap_or(false) {
+        // Hide main, show bubble
+        main_window.hide().map_err(|e| e.to_string())?;
+        bubble_window.show().map_err(|e| e.to_string())?;
+        bubble_window.set_focus().ok();
+        Ok("Switched to bubble mode".to_string())
+    } else {
+        // Show main, keep bubble visible
+        main_window.show().map_err(|e| e.to_string())?;
+        main_window.set_focus().map_err(|e| e.to_string())?;
+        Ok("Switched to main window".to_string())
+    }
+}
+
+#[tauri::command]
+fn minimize_to_bubble(app: tauri::AppHandle) -> Result<String, String> {
+    let main_window = app.get_webview_window("main")
+        .ok_or("Main window not found")?;
+    let bubble_window = app.get_webview_window("bubble")
+        .ok_or("Bubble window not found")?;
+
+    main_window.hide().map_err(|e| e.to_string())?;
+    bubble_window.show().map_err(|e| e.to_string())?;
+    bubble_window.set_focus().ok();
+
+    Ok("Minimized to bubble".to_string())
+}
+
+#[tauri::command]
+fn expand_from_bubble(app: tauri::AppHandle) -> Result<String, String> {
+    let main_window = app.get_webview_window("main")
+        .ok_or("Main window not found")?;
+
+    main_window.show().map_err(|e| e.to_string())?;
+    main_window.set_focus().map_err(|e| e.to_string())?;
+
+    Ok("Expanded to main window".to_string())
+}
+
+// Tauri reports no pointer enter/leave for windows, and a frameless
+// always-on-top webview doesn't reliably get mouseenter, so a watcher polls
+// the cursor against the bubble's bounds. "bubble-hovered" (true on enter,
+// false on leave) is emitted on every change.
+#[derive(Default)]
+struct BubbleHover {
+    // Bumped on every enable/disable; a watcher stops once it's outdated
+    generation: Arc<std::sync::atomic::AtomicU64>,
+}
+
+// expand_after_ms: also expand to the main window once the pointer has
+// rested on the bubble that long
+#[tauri::command]
+fn set_bubble_hover_expand(app: tauri::AppHandle, enabled: bool, expand_after_ms: Option<u64>, hover: tauri::State<'_, BubbleHover>) -> String {
+    let generation = hover.generation.fetch_add(1, Ordering::SeqCst) + 1;
+    if !enabled {
+        return "Bubble hover detection disabled".to_string();
+    }
+
+    let current = hover.generation.clone();
+    std::thread::spawn(move || {
+        let mut hovered_since: Option<std::time::Instant> = None;
+        let mut expanded = false;
+        while current.load(Ordering::SeqCst) == generation {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            let Some(bubble) = app.get_webview_window("bubble") else {
+                continue;
+            };
+            let inside = match (app.cursor_position(), bubble.outer_position(), bubble.outer_size()) {
+                (Ok(cursor), Ok(position), Ok(size)) => {
+                    bubble.is_visible().unwrap_or(false)
+                        && cursor.x >= position.x as f64
+                        && cursor.y >= position.y as f64
+                        && cursor.x < (position.x + size.width as i32) as f64
+                        && cursor.y < (position.y + size.height as i32) as f64
+                }
+                _ => false,
+            };
+
+            if inside != hovered_since.is_some() {
+                hovered_since = inside.then(std::time::Instant::now);
+                expanded = false;
+                app.emit("bubble-hovered", inside).ok();
+            }
+            if let (Some(since), Some(delay)) = (hovered_since, expand_after_ms) {
+                if !expanded && since.elapsed() >= std::time::Duration::from_millis(delay) {
+                    expanded = true;
+                    expand_from_bubble(app.clone()).ok();
+                }
+            }
+        }
+    });
+
+    match expand_after_ms {
+        Some(delay) => format!("Bubble hover detection enabled, expanding after {}ms", delay),
+        None => "Bubble hover detection enabled".to_string(),
+    }
+}
+
+// Sizes are in CSS (logical) pixels, as measured by the frontend's DOM
+#[tauri::command]
+fn resize_bubble_to_content(app: tauri::AppHandle, width: u32, height: u32, anchor: Option<String>) -> Result<String, String> {
+    let bubble_window = app.get_webview_window("bubble")
+        .ok_or("Bubble window not found")?;
+
+    let width = width.clamp(60, 600);
+    let height = height.clamp(60, 800);
+
+    let scale = bubble_window.scale_factor().map_err(|e| e.to_string())?;
+    let old_position = bubble_window.outer_position().map_err(|e| e.to_string())?;
+    let old_size = bubble_window.outer_size().map_err(|e| e.to_string())?;
+
+    bubble_window.set_size(tauri::LogicalSize::new(width, height))
+        .map_err(|e| e.to_string())?;
+
+    // Keep the chosen corner where it was so the bubble grows away from it
+    if let Some(anchor) = anchor {
+        let new_width = (width as f64 * scale) as i32;
+        let new_height = (height as f64 * scale) as i32;
+        let right = old_position.x + old_size.width as i32;
+        let bottom = old_position.y + old_size.height as i32;
+
+        let (x, y) = match anchor.as_str() {
+            "top-left" => (old_position.x, old_position.y),
+            "top-right" => (right - new_width, old_position.y),
+            "bottom-left" => (old_position.x, bottom - new_height),
+            "bottom-right" => (right - new_width, bottom - new_height),
+            _ => return Err(format!("Invalid anchor: {}. Use 'top-left', 'top-right', 'bottom-left', or 'bottom-right'", anchor)),
+        };
+        bubble_window.set_position(tauri::PhysicalPosition::new(x, y))
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(format!("Resized bubble to {}x{}", width, height))
+}
+
+// Scales the assistant's own UI for accessibility; the frontend remembers the choice
+#[tauri::command]
+fn set_webview_zoom(app: tauri::AppHandle, label: String, factor: f64) -> Result<f64, String> {
+    if label != "main" && label != "bubble" {
+        return Err(format!("Invalid window: {}. Use 'main' or 'bubble'", label));
+    }
+    let window = app.get_webview_window(&label)
+        .ok_or(format!("{} window not found", label))?;
+
+    let factor = factor.clamp(0.5, 3.0);
+    window.set_zoom(factor).map_err(|e| e.to_string())?;
+    Ok(factor)
+}
+
+// The bubble is meant to be frameless and the main window framed, but
+// either can be switched at runtime
+#[tauri::command]
+fn set_window_decorations(app: tauri::AppHandle, label: String, enabled: bool) -> Result<bool, String> {
+    if label != "main" && label != "bubble" {
+        return Err(format!("Invalid window: {}. Use 'main' or 'bubble'", label));
+    }
+    let window = app.get_webview_window(&label)
+        .ok_or(format!("{} window not found", label))?;
+
+    window.set_decorations(enabled).map_err(|e| e.to_string())?;
+    window.is_decorated().map_err(|e| e.to_string())
+}
+
+// Stacking level of our windows: "normal", "floating" (always on top) or
+// "screen-saver". On macOS screen-saver is NSScreenSaverWindowLevel on every
+// Space, so the window also shows over full-screen apps. Windows and X11 have
+// a single topmost band, which already covers borderless full-screen video;
+// there screen-saver is the same as floating.
+#[derive(Default)]
+struct WindowLevels {
+    // Labels currently at the screen-saver level
+    screen_saver: Mutex<std::collections::HashSet<String>>,
+}
+
+// Sets or clears the screen-saver level and full-screen auxiliary behavior on
+// an NSWindow. AppKit calls must run on the main thread.
+#[cfg(target_os = "macos")]
+fn set_ns_window_overlay(ns_window: usize, enabled: bool) {
+    use std::ffi::{c_char, c_void};
+
+    #[link(name = "objc")]
+    extern "C" {
+        fn sel_registerName(name: *const c_char) -> *mut c_void;
+        fn objc_msgSend();
+    }
+    const SCREEN_SAVER_WINDOW_LEVEL: isize = 1000;
+    const FULL_SCREEN_AUXILIARY: usize = 1 << 8;
+
+    unsafe {
+        let window = ns_window as *mut c_void;
+        let send = objc_msgSend as unsafe extern "C" fn();
+        let get_usize: unsafe extern "C" fn(*mut c_void, *mut c_void) -> usize = std::mem::transmute(send);
+        let set_usize: unsafe extern "C" fn(*mut c_void, *mut c_void, usize) = std::mem::transmute(send);
+        let set_isize: unsafe extern "C" fn(*mut c_void, *mut c_void, isize) = std::mem::transmute(send);
+
+        let behavior = get_usize(window, sel_registerName(c"collectionBehavior".as_ptr()));
+        let behavior = if enabled { behavior | FULL_SCREEN_AUXILIARY } else { behavior & !FULL_SCREEN_AUXILIARY };
+        set_usize(window, sel_registerName(c"setCollectionBehavior:".as_ptr()), behavior);
+        if enabled {
+            set_isize(window, sel_registerName(c"setLevel:".as_ptr()), SCREEN_SAVER_WINDOW_LEVEL);
+        }
+    }
+}
+
+#[tauri::command]
+fn set_window_level(app: tauri::AppHandle, label: String, level: String, levels: tauri::State<'_, WindowLevels>) -> Result<String, String> {
+    let window = app.get_webview_window(&label)
+        .ok_or(format!("{} window not found", label))?;
+    let level = level.to_lowercase();
+    let screen_saver = match level.as_str() {
+        "normal" | "floating" => false,
+        "screen-saver" => true,
+        _ => return Err(format!("Invalid window level: {}. Use 'normal', 'floating', or 'screen-saver'", level)),
+    };
+
+    // Tauri's own calls set the normal and floating levels
+    window.set_always_on_top(level != "normal").map_err(|e| e.to_string())?;
+    #[cfg(target_os = "macos")]
+    {
+        let was_screen_saver = levels.screen_saver.lock().unwrap().contains(&label);
+        if screen_saver || was_screen_saver {
+            window.set_visible_on_all_workspaces(screen_saver).map_err(|e| e.to_string())?;
+            let ns_window = window.ns_window().map_err(|e| e.to_string())? as usize;
+            window.run_on_main_thread(move || set_ns_window_overlay(ns_window, screen_saver))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let mut screen_saver_labels = levels.screen_saver.lock().unwrap();
+    if screen_saver {
+        screen_saver_labels.insert(label.clone());
+    } else {
+        screen_saver_labels.remove(&label);
+    }
+    Ok(format!("{} window level set to {}", label, level))
+}
+
+#[tauri::command]
+fn get_window_level(app: tauri::AppHandle, label: String, levels: tauri::State<'_, WindowLevels>) -> Result<String, String> {
+    let window = app.get_webview_window(&label)
+        .ok_or(format!("{} window not found", label))?;
+    if levels.screen_saver.lock().unwrap().contains(&label) {
+        return Ok("screen-saver".to_string());
+    }
+    let on_top = window.is_always_on_top().map_err(|e| e.to_string())?;
+    Ok(if on_top { "floating" } else { "normal" }.to_string())
+}
+
+// Cursor shown over our windows. Standard names go through the native
+// cursor API; anything else is taken as the path of an image (browsers cap
+// cursor images at 128x128, 32x32 is safest), which only CSS can show.
+// "default" resets both.
+#[tauri::command]
+fn set_cursor(app: tauri::AppHandle, cursor: String) -> Result<String, String> {
+    use base64::Engine;
+
+    let icon = match cursor.to_lowercase().as_str() {
+        "default" => Some(tauri::CursorIcon::Default),
+        "arrow" => Some(tauri::CursorIcon::Arrow),
+        "hand" | "pointer" => Some(tauri::CursorIcon::Hand),
+        "wait" => Some(tauri::CursorIcon::Wait),
+        "progress" => Some(tauri::CursorIcon::Progress),
+        "crosshair" => Some(tauri::CursorIcon::Crosshair),
+        "text" => Some(tauri::CursorIcon::Text),
+        "move" => Some(tauri::CursorIcon::Move),
+        "help" => Some(tauri::CursorIcon::Help),
+        "not_allowed" => Some(tauri::CursorIcon::NotAllowed),
+        _ => None,
+    };
+
+    // A style tag that overrides every element's cursor, or null to remove it
+    let css = match icon {
+        Some(_) => None,
+        None => {
+            let path = std::path::Path::new(&cursor);
+            let bytes = std::fs::read(path).map_err(|e| format!("Unknown cursor '{}' and not a readable image: {}", cursor, e))?;
+            let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+            Some(format!(
+                "*, *::before, *::after {{ cursor: url(\"data:{};base64,{}\") 0 0, auto !important; }}",
+                guess_mime_type(path),
+                data
+            ))
+        }
+    };
+    let script = format!(
+        "(() => {{ const css = {}; let style = document.getElementById('pal-cursor'); \
+         if (css === null) {{ if (style) style.remove(); return; }} \
+         if (!style) {{ style = document.createElement('style'); style.id = 'pal-cursor'; document.head.appendChild(style); }} \
+         style.textContent = css; }})();",
+        serde_json::to_string(&css).map_err(|e| e.to_string())?
+    );
+
+    for window in app.webview_windows().values() {
+        window.set_cursor_icon(icon.unwrap_or(tauri::CursorIcon::Default)).map_err(|e| e.to_string())?;
+        window.eval(&script).map_err(|e| e.to_string())?;
+    }
+    Ok(format!("Cursor set to {}", cursor))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct WindowState {
+    label: String,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    visible: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct WindowLayout {
+    windows: Vec<WindowState>,
+}
+
+#[tauri::command]
+fn save_window_layout(app: tauri::AppHandle) -> Result<WindowLayout, String> {
+    let mut windows = Vec::new();
+
+    for label in ["main", "bubble"] {
+        let window = app.get_webview_window(label)
+            .ok_or(format!("Window not found: {}", label))?;
+        // Paired the way restore applies them: set_position places the outer
+        // frame, set_size sizes the client area
+        let position = window.outer_position().map_err(|e| e.to_string())?;
+        let size = window.inner_size().map_err(|e| e.to_string())?;
+
+        windows.push(WindowState {
+            label: label.to_string(),
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            visible: window.is_visible().unwrap_or(false),
+        });
+    }
+
+    Ok(WindowLayout { windows })
+}
+
+#[tauri::command]
+fn restore_window_layout(app: tauri::AppHandle, layout: WindowLayout) -> Result<String, String> {
+    for state in &layout.windows {
+        let window = app.get_webview_window(&state.label)
+            .ok_or(format!("Window not found: {}", state.label))?;
+
+        window.set_position(tauri::PhysicalPosition::new(state.x, state.y))
+            .map_err(|e| e.to_string())?;
+        window.set_size(tauri::PhysicalSize::new(state.width, state.height))
+            .map_err(|e| e.to_string())?;
+
+        if state.visible {
+            window.show().map_err(|e| e.to_string())?;
+        } else {
+            window.hide().map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(format!("Restored layout of {} windows", layout.windows.len()))
+}
+
+// Show a pulsing ring centred on a screen point (physical pixels) so users
+// can see where the next click is going to land. The ring ignores the mouse
+// and never takes focus, so it doesn't get in the way of that click.
+#[tauri::command(async)]
+fn highlight_point(app: tauri::AppHandle, x: i32, y: i32, duration_ms: u64, automation: tauri::State<'_, Automation>) -> Result<String, String> {
+    let token = automation.token();
+    static NEXT_ID: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+    const SIZE: u32 = 64;
+
+    // Unique label so overlapping highlights don't collide
+    let label = format!("highlight-{}", NEXT_ID.fetch_add(1, Ordering::SeqCst));
+    let overlay = tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::App("highlight.html".into()))
+        .decorations(false)
+        .transparent(true)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(false)
+        .focused(false)
+        .shadow(false)
+        .visible(false)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let shown = overlay.set_ignore_cursor_events(true)
+        .and_then(|_| overlay.set_size(tauri::PhysicalSize::new(SIZE, SIZE)))
+        .and_then(|_| overlay.set_position(tauri::PhysicalPosition::new(x - SIZE as i32 / 2, y - SIZE as i32 / 2)))
+        .and_then(|_| overlay.show());
+    let waited = match shown {
+        Ok(()) => automation.sleep(token, std::time::Duration::from_millis(duration_ms)),
+        Err(e) => Err(e.to_string()),
+    };
+    overlay.close().ok();
+
+    waited?;
+    Ok(format!("Highlighted ({}, {}) for {}ms", x, y, duration_ms))
+}
+
+// Virtual desktops ("workspaces" on Linux).
+// - Windows has no public API to list or switch desktops. The list comes
+//   from Explorer's registry state and switching presses Ctrl+Win+Left/Right.
+// - Linux uses wmctrl (EWMH window managers).
+// - macOS Spaces can't be listed or switched programmatically, so both
+//   commands return an error there.
+#[derive(serde::Serialize)]
+struct VirtualDesktops {
+    // Index of the active desktop in `desktops`
+    current: u32,
+    desktops: Vec<String>,
+}
+
+// Raw registry value under HKEY_CURRENT_USER; `flags` picks the value type
+#[cfg(target_os = "windows")]
+fn read_user_registry(subkey: &str, value: &str, flags: u32) -> Option<Vec<u8>> {
+    read_registry(windows_sys::Win32::System::Registry::HKEY_CURRENT_USER, subkey, value, flags).map(|(_, data)| data)
+}
+
+// The value's type (REG_SZ, REG_DWORD ...) and raw data
+#[cfg(windows)]
+fn read_registry(
+    hive: windows_sys::Win32::System::Registry::HKEY,
+    subkey: &str,
+    value: &str,
+    flags: u32,
+) -> Option<(u32, Vec<u8>)> {
+    use windows_sys::Win32::System::Registry::RegGetValueW;
+
+    let (subkey, value) = (wide_null(subkey), wide_null(value));
+    unsafe {
+        let mut kind = 0u32;
+        let mut size = 0u32;
+        if RegGetValueW(hive, subkey.as_ptr(), value.as_ptr(), flags, std::ptr::null_mut(), std::ptr::null_mut(), &mut size) != 0 {
+            return None;
+        }
+        let mut data = vec![0u8; size as usize];
+        if RegGetValueW(hive, subkey.as_ptr(), value.as_ptr(), flags, &mut kind, data.as_mut_ptr() as *mut _, &mut size) != 0 {
+            return None;
+        }
+        data.truncate(size as usize);
+        Some((kind, data))
+    }
+}
+
+#[cfg(windows)]
+fn wide_null(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[tauri::command]
+fn list_virtual_desktops() -> Result<VirtualDesktops, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::System::Registry::{RRF_RT_REG_BINARY, RRF_RT_REG_SZ};
+        use windows_sys::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+
+        const KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Explorer\VirtualDesktops";
+        // Desktop ids are GUIDs stored back to back, 16 bytes each
+        let ids = read_user_registry(KEY, "VirtualDesktopIDs", RRF_RT_REG_BINARY)
+            .ok_or("Failed to read the virtual desktop list")?;
+        // Windows 11 keeps the current desktop here, Windows 10 per session
+        let current = read_user_registry(KEY, "CurrentVirtualDesktop", RRF_RT_REG_BINARY).or_else(|| {
+            let mut session = 0u32;
+            unsafe { ProcessIdToSessionId(std::process::id(), &mut session) };
+            read_user_registry(
+                &format!(r"Software\Microsoft\Windows\CurrentVersion\Explorer\SessionInfo\{}\VirtualDesktops", session),
+                "CurrentVirtualDesktop",
+                RRF_RT_REG_BINARY,
+            )
+        });
+
+        let ids: Vec<&[u8]> = ids.chunks_exact(16).collect();
+        let desktops = ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| {
+                let guid = format!(
+                    "{{{:08X}-{:04X}-{:04X}-{}-{}}}",
+                    u32::from_le_bytes([id[0], id[1], id[2], id[3]]),
+                    u16::from_le_bytes([id[4], id[5]]),
+                    u16::from_le_bytes([id[6], id[7]]),
+                    id[8..10].iter().map(|b| format!("{:02X}", b)).collect::<String>(),
+                    id[10..16].iter().map(|b| format!("{:02X}", b)).collect::<String>()
+                );
+                // Only desktops the user renamed have a Name; the rest show as "Desktop N"
+                read_user_registry(&format!(r"{}\Desktops\{}", KEY, guid), "Name", RRF_RT_REG_SZ)
+                    .map(|name| {
+                        let wide: Vec<u16> = name.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+                        String::from_utf16_lossy(&wide).trim_end_matches('\0').to_string()
+                    })
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| format!("Desktop {}", index + 1))
+            })
+            .collect();
+        let current = current
+            .and_then(|current| ids.iter().position(|id| *id == current.as_slice()))
+            .unwrap_or(0) as u32;
+        Ok(VirtualDesktops { current, desktops })
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Err("macOS Spaces can't be listed programmatically".to_string())
+    }
+
+    // Lines look like: 0  * DG: 1920x1080  VP: 0,0  WA: 0,0 1920x1050  Desktop 1
+    #[cfg(target_os = "linux")]
+    {
+        let output = Command::new("wmctrl")
+            .arg("-d")
+            .output()
+            .map_err(|e| format!("Failed to run wmctrl (is it installed?): {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+
+        let mut current = 0;
+        let mut desktops = Vec::new();
+        for (index, line) in String::from_utf8_lossy(&output.stdout).lines().enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(1) == Some(&"*") {
+                current = index as u32;
+            }
+            let name = fields.get(9..).map(|name| name.join(" ")).unwrap_or_default();
+            desktops.push(if name.is_empty() { format!("Desktop {}", index + 1) } else { name });
+        }
+        Ok(VirtualDesktops { current, desktops })
+    }
+}
+
+#[tauri::command(async)]
+fn switch_virtual_desktop(index: u32, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let desktops = list_virtual_desktops()?;
+    if index as usize >= desktops.desktops.len() {
+        return Err(format!("Virtual desktop not found: {}", index));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // One Ctrl+Win+arrow per desktop between here and the target
+        let steps = index as i64 - desktops.current as i64;
+        let arrow = if steps > 0 { Key::RightArrow } else { Key::LeftArrow };
+        let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+        for _ in 0..steps.abs() {
+            limiter.wait();
+            enigo.key(Key::Control, Direction::Press).map_err(describe_enigo_error)?;
+            limiter.wait();
+            enigo.key(Key::Meta, Direction::Press).map_err(describe_enigo_error)?;
+            limiter.wait();
+            let pressed = enigo.key(arrow, Direction::Click);
+            limiter.wait();
+            enigo.key(Key::Meta, Direction::Release).map_err(describe_enigo_error)?;
+            enigo.key(Key::Control, Direction::Release).map_err(describe_enigo_error)?;
+            pressed.map_err(describe_enigo_error)?;
+            // Let the switch animation finish or the next press can be dropped
+            std::thread::sleep(std::time::Duration::from_millis(250));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let _ = &limiter;
+        let output = Command::new("wmctrl")
+            .args(["-s", &index.to_string()])
+            .output()
+            .map_err(|e| format!("Failed to run wmctrl (is it installed?): {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    let _ = &limiter;
+
+    Ok(format!("Switched to virtual desktop {} ({})", index, desktops.desktops[index as usize]))
+}
+
+// Show desktop: Shell.Application's MinimizeAll on Windows and wmctrl's
+// showing-desktop mode on Linux, which both know how to undo themselves.
+// macOS has no scriptable equivalent, so visible apps are hidden and the
+// ones hidden here are remembered for restore_all_windows.
+#[derive(Default)]
+struct DesktopReveal {
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    hidden_apps: Mutex<Vec<String>>,
+}
+
+#[cfg(target_os = "macos")]
+const HIDE_APPS_SCRIPT: &str = r#"on run argv
+    tell application "System Events"
+        set names to name of every process whose visible is true and background only is false and unix id is not (item 1 of argv as integer)
+        repeat with appName in names
+            set visible of process appName to false
+        end repeat
+    end tell
+    set AppleScript's text item delimiters to linefeed
+    return names as text
+end run"#;
+
+fn run_desktop_tool(program: &str, args: &[&str]) -> Result<(), String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {} (is it installed?): {}", program, e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+    Ok(())
+}
+
+// The bubble stays up so the assistant is still reachable
+#[tauri::command(async)]
+fn minimize_all_windows(app: tauri::AppHandle, reveal: tauri::State<'_, DesktopReveal>) -> Result<String, String> {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = &reveal;
+        run_desktop_tool("powershell", &["-NoProfile", "-Command", "(New-Object -ComObject Shell.Application).MinimizeAll()"])?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let output = Command::new("osascript")
+            .args(["-e", HIDE_APPS_SCRIPT, &std::process::id().to_string()])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+        let mut hidden = reveal.hidden_apps.lock().unwrap();
+        for name in String::from_utf8_lossy(&output.stdout).lines().filter(|name| !name.is_empty()) {
+            if !hidden.iter().any(|hidden_name| hidden_name == name) {
+                hidden.push(name.to_string());
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let _ = &reveal;
+        run_desktop_tool("wmctrl", &["-k", "on"])?;
+    }
+
+    if let Some(bubble) = app.get_webview_window("bubble") {
+        // Windows are minimized asynchronously; wait before bringing the bubble back
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        bubble.unminimize().ok();
+        bubble.show().ok();
+    }
+    Ok("Minimized all windows".to_string())
+}
+
+#[tauri::command(async)]
+fn restore_all_windows(reveal: tauri::State<'_, DesktopReveal>) -> Result<String, String> {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = &reveal;
+        run_desktop_tool("powershell", &["-NoProfile", "-Command", "(New-Object -ComObject Shell.Application).UndoMinimizeALL()"])?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let names = std::mem::take(&mut *reveal.hidden_apps.lock().unwrap());
+        if !names.is_empty() {
+            let lines: Vec<String> = names.iter()
+                .map(|name| format!("try\nset visible of process {} to true\nend try", applescript_string(name)))
+                .collect();
+            let script = format!("tell application \"System Events\"\n{}\nend tell", lines.join("\n"));
+            run_desktop_tool("osascript", &["-e", &script])?;
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let _ = &reveal;
+        run_desktop_tool("wmctrl", &["-k", "off"])?;
+    }
+
+    Ok("Restored all windows".to_string())
+}
+
+// Display commands
+// Display ids are indexes into the monitor list, in the order the OS reports them
+#[derive(Clone, PartialEq, serde::Serialize)]
+struct DisplayInfo {
+    id: u32,
+    name: Option<String>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    scale_factor: f64,
+    is_primary: bool,
+}
+
+fn monitor_contains(monitor: &tauri::Monitor, x: i32, y: i32) -> bool {
+    let position = monitor.position();
+    let size = monitor.size();
+    x >= position.x
+        && y >= position.y
+        && (x as i64) < position.x as i64 + size.width as i64
+        && (y as i64) < position.y as i64 + size.height as i64
+}
+
+#[tauri::command]
+fn get_displays(app: tauri::AppHandle) -> Result<Vec<DisplayInfo>, String> {
+    display_layout(&app)
+}
+
+fn display_layout(app: &tauri::AppHandle) -> Result<Vec<DisplayInfo>, String> {
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    let primary = app.primary_monitor().map_err(|e| e.to_string())?;
+
+    Ok(monitors
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| DisplayInfo {
+            id: index as u32,
+            name: monitor.name().cloned(),
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+            scale_factor: monitor.scale_factor(),
+            is_primary: primary.as_ref().is_some_and(|p| p.position() == monitor.position()),
+        })
+        .collect())
+}
+
+#[tauri::command]
+fn get_cursor_display(app: tauri::AppHandle) -> Result<u32, String> {
+    let cursor = app.cursor_position().map_err(|e| e.to_string())?;
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+
+    monitors
+        .iter()
+        .position(|monitor| monitor_contains(monitor, cursor.x as i32, cursor.y as i32))
+        .map(|index| index as u32)
+        .ok_or("Cursor is not on any display".to_string())
+}
+
+// Usable area of a display in physical pixels, excluding the taskbar, dock
+// and menu bar (get_displays reports the full bounds)
+#[tauri::command]
+fn get_work_area(app: tauri::AppHandle, display_id: u32) -> Result<WindowRect, String> {
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    let monitor = monitors.get(display_id as usize)
+        .ok_or(format!("Display not found: {}", display_id))?;
+    let area = monitor.work_area();
+    Ok(WindowRect {
+        x: area.position.x,
+        y: area.position.y,
+        width: area.size.width,
+        height: area.size.height,
+    })
+}
+
+// Scale factor of the display under a point; `logical` says which space the
+// point is in. Points off every display use the primary display's factor.
+fn scale_factor_at(app: &tauri::AppHandle, x: f64, y: f64, logical: bool) -> Result<f64, String> {
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    let scale = monitors.iter()
+        .find(|monitor| {
+            let to_space = if logical { monitor.scale_factor() } else { 1.0 };
+            let (px, py) = ((x * to_space).round() as i32, (y * to_space).round() as i32);
+            monitor_contains(monitor, px, py)
+        })
+        .map(|monitor| monitor.scale_factor());
+
+    match scale {
+        Some(scale) => Ok(scale),
+        None => Ok(app.primary_monitor().map_err(|e| e.to_string())?.map(|m| m.scale_factor()).unwrap_or(1.0)),
+    }
+}
+
+// DOM/CSS coordinates are logical, enigo and capture work in physical pixels
+#[tauri::command]
+fn logical_to_physical(app: tauri::AppHandle, x: f64, y: f64) -> Result<(i32, i32), String> {
+    let scale = scale_factor_at(&app, x, y, true)?;
+    Ok(((x * scale).round() as i32, (y * scale).round() as i32))
+}
+
+#[tauri::command]
+fn physical_to_logical(app: tauri::AppHandle, x: i32, y: i32) -> Result<(i32, i32), String> {
+    let scale = scale_factor_at(&app, x as f64, y as f64, false)?;
+    Ok(((x as f64 / scale).round() as i32, (y as f64 / scale).round() as i32))
+}
+
+// Primary display refresh rate in Hz, or None if the platform won't say
+// (e.g. Linux under Wayland, where xrandr has no real modes to report)
+#[tauri::command]
+fn get_refresh_rate() -> Option<u32> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Graphics::Gdi::{EnumDisplaySettingsW, DEVMODEW, ENUM_CURRENT_SETTINGS};
+
+        unsafe {
+            let mut mode: DEVMODEW = std::mem::zeroed();
+            mode.dmSize = std::mem::size_of::<DEVMODEW>() as u16;
+            // 0 and 1 both mean "hardware default", i.e. unknown
+            if EnumDisplaySettingsW(std::ptr::null(), ENUM_CURRENT_SETTINGS, &mut mode) != 0 && mode.dmDisplayFrequency > 1 {
+                Some(mode.dmDisplayFrequency)
+            } else {
+                None
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // One block per display, e.g. "UI Looks like: 1512 x 982 @ 120.00Hz"
+        let output = Command::new("system_profiler").arg("SPDisplaysDataType").output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout).to_string();
+        let mut blocks = vec![Vec::new()];
+        for line in text.lines() {
+            if line.trim_end().ends_with(':') {
+                blocks.push(Vec::new());
+            } else if let Some(block) = blocks.last_mut() {
+                block.push(line.trim());
+            }
+        }
+        let rate = |block: &Vec<&str>| block.iter().find_map(|line| {
+            let hz = line.find("Hz")?;
+            let number = line[..hz].trim_end().rsplit(|c: char| !(c.is_ascii_digit() || c == '.')).next()?;
+            number.parse::<f64>().ok().map(|hz| hz.round() as u32)
+        });
+        blocks.iter()
+            .find(|block| block.contains(&"Main Display: Yes"))
+            .and_then(rate)
+            .or_else(|| blocks.iter().find_map(rate))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // The current mode is marked with '*', e.g. "1920x1080  60.00*+  59.94"
+        let output = Command::new("xrandr").arg("--current").output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout).to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        let start = lines.iter().position(|line| line.contains(" connected primary")).unwrap_or(0);
+        lines[start..].iter()
+            .flat_map(|line| line.split_whitespace())
+            .find(|token| token.contains('*'))
+            .and_then(|token| token.trim_end_matches(['*', '+']).parse::<f64>().ok())
+            .map(|hz| hz.round() as u32)
+    }
+}
+
+// Screen capture commands
+// Capture a region of the virtual desktop, given in physical pixels.
+// `scale_factor` is only needed on macOS, where screencapture takes points.
+fn capture_screen_region(x: i32, y: i32, width: u32, height: u32, scale_factor: f64) -> Result<image::RgbaImage, String> {
+    if width == 0 || height == 0 {
+        return Err("Capture region is empty".to_string());
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Graphics::Gdi::*;
+
+        let _ = scale_factor;
+        let (w, h) = (width as i32, height as i32);
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+
+        unsafe {
+            let screen = GetDC(std::ptr::null_mut());
+            let memory = CreateCompatibleDC(screen);
+            let bitmap = CreateCompatibleBitmap(screen, w, h);
+            let previous = SelectObject(memory, bitmap);
+
+            let copied = BitBlt(memory, 0, 0, w, h, screen, x, y, SRCCOPY | CAPTUREBLT) != 0;
+
+            let mut info: BITMAPINFO = std::mem::zeroed();
+            info.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+            info.bmiHeader.biWidth = w;
+            info.bmiHeader.biHeight = -h; // negative = top-down rows
+            info.bmiHeader.biPlanes = 1;
+            info.bmiHeader.biBitCount = 32;
+            info.bmiHeader.biCompression = BI_RGB;
+            let rows = GetDIBits(memory, bitmap, 0, height, pixels.as_mut_ptr() as *mut _, &mut info, DIB_RGB_COLORS);
+
+            SelectObject(memory, previous);
+            DeleteObject(bitmap);
+            DeleteDC(memory);
+            ReleaseDC(std::ptr::null_mut(), screen);
+
+            if !copied || rows == 0 {
+                return Err("Failed to capture the screen".to_string());
+            }
+        }
+
+        // GDI hands back BGRA with an undefined alpha channel
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+            pixel[3] = 255;
+        }
+        image::RgbaImage::from_raw(width, height, pixels).ok_or("Invalid capture buffer".to_string())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let path = std::env::temp_dir().join(format!("goldman-capture-{}.png", std::process::id()));
+        let region = format!(
+            "-R{},{},{},{}",
+            (x as f64 / scale_factor).round(),
+            (y as f64 / scale_factor).round(),
+            (width as f64 / scale_factor).round(),
+            (height as f64 / scale_factor).round()
+        );
+        let output = Command::new("screencapture")
+            .args(["-x", "-t", "png", &region])
+            .arg(&path)
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(format!("screencapture failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let captured = image::open(&path).map_err(|e| e.to_string());
+        std::fs::remove_file(&path).ok();
+        Ok(captured?.to_rgba8())
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let _ = scale_factor;
+        // grim on Wayland, ImageMagick's import on X11; both write PNG to stdout
+        let output = if std::env::var("WAYLAND_DISPLAY").is_ok() {
+            Command::new("grim")
+                .args(["-g", &format!("{},{} {}x{}", x, y, width, height), "-"])
+                .output()
+        } else {
+            Command::new("import")
+                .args(["-window", "root", "-crop", &format!("{}x{}+{}+{}", width, height, x, y), "png:-"])
+                .output()
+        }
+        .map_err(|e| format!("Failed to run screen capture tool (is grim or ImageMagick installed?): {}", e))?;
+        if !output.status.success() {
+            return Err(format!("Screen capture failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        image::load_from_memory_with_format(&output.stdout, image::ImageFormat::Png)
+            .map(|captured| captured.to_rgba8())
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn encode_png_base64(image: &image::RgbaImage) -> Result<String, String> {
+    use base64::Engine;
+
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut bytes, image::ImageFormat::Png).map_err(|e| e.to_string())?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(bytes.into_inner()))
+}
+
+// Union of all monitors in physical pixels: (x, y, width, height)
+fn virtual_desktop_bounds(app: &tauri::AppHandle) -> Result<(i32, i32, u32, u32), String> {
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    if monitors.is_empty() {
+        return Err("No displays found".to_string());
+    }
+
+    let left = monitors.iter().map(|m| m.position().x).min().unwrap_or(0);
+    let top = monitors.iter().map(|m| m.position().y).min().unwrap_or(0);
+    let right = monitors.iter().map(|m| m.position().x + m.size().width as i32).max().unwrap_or(0);
+    let bottom = monitors.iter().map(|m| m.position().y + m.size().height as i32).max().unwrap_or(0);
+    Ok((left, top, (right - left) as u32, (bottom - top) as u32))
+}
+
+// Rectangle in CSS pixels relative to the selection overlay
+#[derive(serde::Deserialize)]
+struct SelectionRect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+#[derive(Default)]
+struct RegionSelection {
+    sender: Mutex<Option<std::sync::mpsc::Sender<Option<SelectionRect>>>>,
+}
+
+#[derive(serde::Serialize)]
+struct RegionCapture {
+    image_base64: String,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+#[tauri::command(async)]
+fn capture_interactive_region(app: tauri::AppHandle, selection: tauri::State<'_, RegionSelection>) -> Result<RegionCapture, String> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    *selection.sender.lock().unwrap() = Some(sender);
+
+    let (left, top, width, height) = virtual_desktop_bounds(&app)?;
+    let overlay = tauri::WebviewWindowBuilder::new(&app, "region-select", tauri::WebviewUrl::App("region-select.html".into()))
+        .decorations(false)
+        .transparent(true)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(false)
+        .visible(false)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    // Closing the overlay by any other means counts as a cancel
+    let state_app = app.clone();
+    overlay.on_window_event(move |event| {
+        if let tauri::WindowEvent::Destroyed = event {
+            if let Some(sender) = state_app.state::<RegionSelection>().sender.lock().unwrap().take() {
+                sender.send(None).ok();
+            }
+        }
+    });
+
+    overlay.set_position(tauri::PhysicalPosition::new(left, top)).map_err(|e| e.to_string())?;
+    overlay.set_size(tauri::PhysicalSize::new(width, height)).map_err(|e| e.to_string())?;
+    overlay.show().map_err(|e| e.to_string())?;
+    overlay.set_focus().ok();
+
+    let selected = receiver.recv().ok().flatten();
+    let scale = overlay.scale_factor().unwrap_or(1.0);
+    overlay.close().ok();
+
+    let rect = selected.ok_or("Region selection cancelled".to_string())?;
+    let x = left + (rect.x * scale).round() as i32;
+    let y = top + (rect.y * scale).round() as i32;
+    let region_width = (rect.width * scale).round() as u32;
+    let region_height = (rect.height * scale).round() as u32;
+
+    // Let the compositor remove the overlay before grabbing the pixels
+    std::thread::sleep(std::time::Duration::from_millis(150));
+    let captured = capture_screen_region(x, y, region_width, region_height, scale)?;
+
+    Ok(RegionCapture {
+        image_base64: encode_png_base64(&captured)?,
+        x,
+        y,
+        width: region_width,
+        height: region_height,
+    })
+}
+
+// Called by region-select.html; `None` means the user pressed Escape
+#[tauri::command]
+fn finish_region_selection(selection: Option<SelectionRect>, state: tauri::State<'_, RegionSelection>) {
+    if let Some(sender) = state.sender.lock().unwrap().take() {
+        sender.send(selection.filter(|rect| rect.width >= 1.0 && rect.height >= 1.0)).ok();
+    }
+}
+
+// One PNG of the whole virtual desktop, with every monitor drawn at its
+// offset. Gaps between monitors of different sizes are left transparent.
+#[tauri::command(async)]
+fn capture_all_displays(app: tauri::AppHandle, path: String) -> Result<String, String> {
+    let (canvas, _, _, displays) = capture_virtual_desktop(&app)?;
+    canvas.save_with_format(&path, image::ImageFormat::Png).map_err(|e| e.to_string())?;
+    Ok(format!("Saved {}x{} capture of {} displays to {}", canvas.width(), canvas.height(), displays, path))
+}
+
+// Returns the stitched capture, its top-left corner in physical pixels and the display count
+fn capture_virtual_desktop(app: &tauri::AppHandle) -> Result<(image::RgbaImage, i32, i32, usize), String> {
+    let (left, top, width, height) = virtual_desktop_bounds(app)?;
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+
+    let mut canvas = image::RgbaImage::new(width, height);
+    for monitor in &monitors {
+        let position = monitor.position();
+        let size = monitor.size();
+        let captured = capture_screen_region(position.x, position.y, size.width, size.height, monitor.scale_factor())?;
+        image::imageops::replace(&mut canvas, &captured, (position.x - left) as i64, (position.y - top) as i64);
+    }
+    Ok((canvas, left, top, monitors.len()))
+}
+
+// Where a template was found, in physical screen pixels
+struct TemplateMatch {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    // 0-1, 1 = identical
+    score: f64,
+}
+
+impl TemplateMatch {
+    fn center(&self) -> (i32, i32) {
+        (self.x + self.width as i32 / 2, self.y + self.height as i32 / 2)
+    }
+}
+
+// Finds a PNG template on screen. Returns the best match, or None if nothing
+// reaches `threshold`. Matching is done on grayscale pixels.
+fn find_template_on_screen(app: &tauri::AppHandle, template_path: &str, threshold: f64) -> Result<Option<TemplateMatch>, String> {
+    use image::imageops::grayscale;
+
+    let template = image::open(template_path).map_err(|e| format!("Failed to load template {}: {}", template_path, e))?;
+    let needle = template.to_luma8();
+    let (screen, left, top, _) = capture_virtual_desktop(app)?;
+    let haystack = grayscale(&screen);
+    if needle.width() == 0 || needle.height() == 0 || needle.width() > haystack.width() || needle.height() > haystack.height() {
+        return Ok(None);
+    }
+
+    // Scan a copy downscaled so the template is still at least
+    // COARSE_TEMPLATE_SIZE pixels across, then refine the best few spots at
+    // full size. Both passes give up on a spot as soon as it can't win.
+    const COARSE_TEMPLATE_SIZE: u32 = 16;
+    // Repeated UI (list rows, toolbar icons) looks alike once shrunk, so keep plenty
+    const CANDIDATES: usize = 64;
+    // Downscaling blurs, so coarse scores run lower than the full-size ones
+    const COARSE_SLACK: f64 = 0.15;
+    let step = (needle.width().min(needle.height()) / COARSE_TEMPLATE_SIZE).clamp(1, 4);
+    let slack = if step > 1 { COARSE_SLACK } else { 0.0 };
+    let (small_haystack, small_needle) = (shrink_gray(&haystack, step), shrink_gray(&needle, step));
+
+    let mut candidates: Vec<(f64, u32, u32)> = Vec::new();
+    for y in 0..=small_haystack.height() - small_needle.height() {
+        for x in 0..=small_haystack.width() - small_needle.width() {
+            let mut min_score = threshold - slack;
+            if candidates.len() == CANDIDATES {
+                min_score = min_score.max(candidates[CANDIDATES - 1].0);
+            }
+            if let Some(score) = template_similarity(&small_haystack, &small_needle, x, y, min_score) {
+                let index = candidates.partition_point(|(kept, _, _)| *kept >= score);
+                candidates.insert(index, (score, x, y));
+                candidates.truncate(CANDIDATES);
+            }
+        }
+    }
+
+    let max_x = haystack.width() - needle.width();
+    let max_y = haystack.height() - needle.height();
+    let mut best: Option<(f64, u32, u32)> = None;
+    for (_, cx, cy) in candidates {
+        for y in (cy * step).saturating_sub(step)..=(cy * step + step).min(max_y) {
+            for x in (cx * step).saturating_sub(step)..=(cx * step + step).min(max_x) {
+                let min_score = best.map_or(threshold, |(best_score, _, _)| best_score.max(threshold));
+                if let Some(score) = template_similarity(&haystack, &needle, x, y, min_score) {
+                    if best.is_none_or(|(best_score, _, _)| score > best_score) {
+                        best = Some((score, x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(best.filter(|(score, _, _)| *score >= threshold).map(|(score, x, y)| TemplateMatch {
+        x: left + x as i32,
+        y: top + y as i32,
+        width: needle.width(),
+        height: needle.height(),
+        score,
+    }))
+}
+
+// Averages each step x step block into one pixel, dropping partial blocks at
+// the edges, so screen and template shrink by exactly the same factor
+fn shrink_gray(image: &image::GrayImage, step: u32) -> image::GrayImage {
+    if step == 1 {
+        return image.clone();
+    }
+    image::GrayImage::from_fn(image.width() / step, image.height() / step, |x, y| {
+        let mut sum = 0u32;
+        for dy in 0..step {
+            for dx in 0..step {
+                sum += image.get_pixel(x * step + dx, y * step + dy).0[0] as u32;
+            }
+        }
+        image::Luma([(sum / (step * step)) as u8])
+    })
+}
+
+// 1 minus the mean absolute pixel difference of `needle` placed at (x, y),
+// or None as soon as the difference so far rules out reaching `min_score`
+fn template_similarity(haystack: &image::GrayImage, needle: &image::GrayImage, x: u32, y: u32, min_score: f64) -> Option<f64> {
+    let width = needle.width() as usize;
+    let pixels = (width * needle.height() as usize) as f64;
+    let budget = ((1.0 - min_score).max(0.0) * pixels * 255.0) as u64;
+    let mut total: u64 = 0;
+    for row in 0..needle.height() {
+        let start = ((y + row) * haystack.width() + x) as usize;
+        let needle_start = row as usize * width;
+        total += haystack.as_raw()[start..start + width]
+            .iter()
+            .zip(&needle.as_raw()[needle_start..needle_start + width])
+            .map(|(a, b)| a.abs_diff(*b) as u64)
+            .sum::<u64>();
+        if total > budget {
+            return None;
+        }
+    }
+    Some(1.0 - total as f64 / pixels / 255.0)
+}
+
+// Moves onto a template image and rests there so hover-only UI (tooltips,
+// menus) appears. threshold is the minimum similarity, 0-1.
+#[tauri::command(async)]
+fn hover_image(
+    app: tauri::AppHandle,
+    template_path: String,
+    threshold: f64,
+    hold_ms: u64,
+    limiter: tauri::State<'_, InputRateLimiter>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<String, String> {
+    let token = automation.token();
+    let found = find_template_on_screen(&app, &template_path, threshold)?
+        .ok_or(format!("Image {} not found on screen", template_path))?;
+    let (x, y) = found.center();
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.move_mouse(x, y, Coordinate::Abs).map_err(describe_enigo_error)?;
+    automation.sleep(token, std::time::Duration::from_millis(hold_ms))?;
+
+    Ok(format!("Hovered image at ({}, {}) for {}ms (similarity {:.2})", x, y, hold_ms, found.score))
+}
+
+// Clicks at (dx, dy) from the top-left corner of an anchor image, so targets
+// near a recognizable logo or label still get hit after the window moves
+#[tauri::command(async)]
+fn click_offset_from_image(app: tauri::AppHandle, template_path: String, dx: i32, dy: i32, threshold: f64, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let found = find_template_on_screen(&app, &template_path, threshold)?
+        .ok_or(format!("Anchor image {} not found on screen", template_path))?;
+    let (x, y) = (found.x + dx, found.y + dy);
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.move_mouse(x, y, Coordinate::Abs).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.button(Button::Left, Direction::Click).map_err(describe_enigo_error)?;
+
+    Ok(format!("Clicked ({}, {}), offset ({}, {}) from anchor at ({}, {})", x, y, dx, dy, found.x, found.y))
+}
+
+// Screen area in physical pixels
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+struct ScreenRegion {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+// Captures `region`, or the whole desktop if None, with its top-left corner
+fn capture_area(app: &tauri::AppHandle, region: Option<ScreenRegion>) -> Result<(image::RgbaImage, i32, i32), String> {
+    match region {
+        Some(region) => {
+            let scale = scale_factor_at(app, region.x as f64, region.y as f64, false)?;
+            Ok((capture_screen_region(region.x, region.y, region.width, region.height, scale)?, region.x, region.y))
+        }
+        None => {
+            let (image, left, top, _) = capture_virtual_desktop(app)?;
+            Ok((image, left, top))
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct OcrWord {
+    text: String,
+    // Screen coordinates in physical pixels
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    // Tesseract's confidence, 0-100
+    confidence: f64,
+}
+
+// Every word tesseract recognizes in `region` (the whole desktop if None),
+// with its on-screen box. Needs the tesseract CLI on the PATH.
+fn ocr_words(app: &tauri::AppHandle, region: Option<ScreenRegion>) -> Result<Vec<OcrWord>, String> {
+    let (image, left, top) = capture_area(app, region)?;
+    let mut png = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut png, image::ImageFormat::Png).map_err(|e| e.to_string())?;
+
+    let mut child = Command::new("tesseract")
+        .args(["stdin", "stdout", "tsv"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run tesseract (is it installed?): {}", e))?;
+    // tesseract reads the whole image before writing anything, so this can't deadlock
+    if let Some(mut stdin) = child.stdin.take() {
+        use std::io::Write;
+        stdin.write_all(png.get_ref()).map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+
+    // Columns: level page block paragraph line word left top width height conf text;
+    // level 5 rows are words, the others are layout containers
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 12 || fields[0] != "5" || fields[11].trim().is_empty() {
+                return None;
+            }
+            Some(OcrWord {
+                text: fields[11].trim().to_string(),
+                x: left + fields[6].parse::<i32>().ok()?,
+                y: top + fields[7].parse::<i32>().ok()?,
+                width: fields[8].parse().ok()?,
+                height: fields[9].parse().ok()?,
+                confidence: fields[10].parse().ok()?,
+            })
+        })
+        .collect())
+}
+
+// Lets the assistant act on text it reads: click the centre of a word's box
+#[tauri::command(async)]
+fn ocr_screen_with_boxes(app: tauri::AppHandle, region: Option<ScreenRegion>) -> Result<Vec<OcrWord>, String> {
+    ocr_words(&app, region)
+}
+
+// Clicks the on-screen text that best matches `target`, which may span
+// several words of one line. Matching is case-insensitive and fuzzy so small
+// OCR mistakes still match; threshold is the minimum similarity, 0-1.
+#[tauri::command(async)]
+fn click_text(app: tauri::AppHandle, target: String, threshold: f64, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let words = ocr_words(&app, None)?;
+    let wanted = target.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let span = wanted.split(' ').count().max(1);
+
+    // Candidates are runs of `span` consecutive words (tesseract lists them in
+    // reading order) whose centres sit on the same line
+    let mut candidates: Vec<(f64, String, i32, i32)> = Vec::new();
+    for run in words.windows(span.min(words.len().max(1))) {
+        let first = &run[0];
+        let same_line = run.iter().all(|word| {
+            let centre = |w: &OcrWord| w.y + w.height as i32 / 2;
+            (centre(word) - centre(first)).unsigned_abs() <= first.height.max(word.height) / 2
+        });
+        if !same_line {
+            continue;
+        }
+        let text = run.iter().map(|word| word.text.as_str()).collect::<Vec<_>>().join(" ");
+        let last = &run[run.len() - 1];
+        let left = first.x;
+        let top = run.iter().map(|word| word.y).min().unwrap_or(first.y);
+        let right = last.x + last.width as i32;
+        let bottom = run.iter().map(|word| word.y + word.height as i32).max().unwrap_or(top);
+        candidates.push((text_similarity(&wanted, &text.to_lowercase()), text, (left + right) / 2, (top + bottom) / 2));
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    match candidates.first() {
+        Some((score, text, x, y)) if *score >= threshold => {
+            let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+            limiter.wait();
+            enigo.move_mouse(*x, *y, Coordinate::Abs).map_err(describe_enigo_error)?;
+            limiter.wait();
+            enigo.button(Button::Left, Direction::Click).map_err(describe_enigo_error)?;
+            Ok(format!("Clicked \"{}\" at ({}, {}) (similarity {:.2})", text, x, y, score))
+        }
+        _ => {
+            let closest = candidates
+                .iter()
+                .take(5)
+                .map(|(score, text, _, _)| format!("\"{}\" ({:.2})", text, score))
+                .collect::<Vec<_>>();
+            if closest.is_empty() {
+                Err(format!("Text \"{}\" not found; no text was recognized on screen", target))
+            } else {
+                Err(format!("Text \"{}\" not found. Closest matches: {}", target, closest.join(", ")))
+            }
+        }
+    }
+}
+
+// 1 minus the Levenshtein distance relative to the longer string
+fn text_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    1.0 - previous[b.len()] as f64 / a.len().max(b.len()) as f64
+}
+
+// The capture screenshot_diff compares against, with its top-left corner
+#[derive(Default)]
+struct ScreenshotBaseline {
+    capture: Mutex<Option<(image::RgbaImage, i32, i32)>>,
+}
+
+#[derive(serde::Serialize)]
+struct ScreenDiff {
+    changed: bool,
+    changed_pixels: u32,
+    // PNG with changed pixels in red on transparent; None when unchanged
+    diff_image_base64: Option<String>,
+}
+
+// Channel differences up to this are treated as noise from dithering and
+// colour management rounding
+const SCREEN_DIFF_TOLERANCE: u8 = 8;
+
+#[tauri::command(async)]
+fn screenshot_baseline(app: tauri::AppHandle, region: Option<ScreenRegion>, baseline: tauri::State<'_, ScreenshotBaseline>) -> Result<String, String> {
+    let capture = capture_area(&app, region)?;
+    let (width, height) = capture.0.dimensions();
+    *baseline.capture.lock().unwrap() = Some(capture);
+    Ok(format!("Stored {}x{} baseline", width, height))
+}
+
+// Captures the same area as the baseline again and compares; the baseline is
+// kept, so repeated calls can wait for the screen to change or settle.
+// `region` must match the one given to screenshot_baseline.
+#[tauri::command(async)]
+fn screenshot_diff(app: tauri::AppHandle, region: Option<ScreenRegion>, baseline: tauri::State<'_, ScreenshotBaseline>) -> Result<ScreenDiff, String> {
+    let (current, left, top) = capture_area(&app, region)?;
+    let stored = baseline.capture.lock().unwrap();
+    let (previous, previous_left, previous_top) = stored.as_ref().ok_or("No baseline stored; call screenshot_baseline first")?;
+    if current.dimensions() != previous.dimensions() || (left, top) != (*previous_left, *previous_top) {
+        return Err("Region differs from the baseline; call screenshot_baseline for this region".to_string());
+    }
+
+    let mut diff = image::RgbaImage::new(current.width(), current.height());
+    let mut changed_pixels = 0u32;
+    for ((now, before), marked) in current.pixels().zip(previous.pixels()).zip(diff.pixels_mut()) {
+        if now.0.iter().zip(before.0.iter()).any(|(a, b)| a.abs_diff(*b) > SCREEN_DIFF_TOLERANCE) {
+            changed_pixels += 1;
+            *marked = image::Rgba([255, 0, 0, 255]);
+        }
+    }
+
+    Ok(ScreenDiff {
+        changed: changed_pixels > 0,
+        changed_pixels,
+        diff_image_base64: if changed_pixels > 0 { Some(encode_png_base64(&diff)?) } else { None },
+    })
+}
+
+// Screen recording
+// A capture thread grabs frames at the requested rate and hands them to an
+// encoder thread through a bounded queue. If encoding falls behind, capture
+// waits instead of buffering full-screen frames without limit. Stopping ends
+// capture first, then the encoder drains the queue and finishes the file.
+const SCREEN_RECORD_QUEUE_FRAMES: usize = 8;
+
+struct ScreenRecording {
+    path: String,
+    stop: Arc<AtomicBool>,
+    capture: std::thread::JoinHandle<Result<(), String>>,
+    encoder: std::thread::JoinHandle<Result<u32, String>>,
+}
+
+#[derive(Default)]
+struct ScreenRecorder {
+    active: Mutex<Option<ScreenRecording>>,
+}
+
+enum RecordingEncoder {
+    Gif(image::codecs::gif::GifEncoder<std::io::BufWriter<std::fs::File>>),
+    // ffmpeg reading raw RGBA frames on stdin
+    Mp4(std::process::Child),
+}
+
+impl RecordingEncoder {
+    fn create(path: &str, width: u32, height: u32, fps: u32) -> Result<Self, String> {
+        let lower = path.to_lowercase();
+        if lower.ends_with(".gif") {
+            let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+            // Fastest palette quantization, so encoding keeps up with capture
+            let mut encoder = image::codecs::gif::GifEncoder::new_with_speed(std::io::BufWriter::new(file), 30);
+            encoder.set_repeat(image::codecs::gif::Repeat::Infinite).map_err(|e| e.to_string())?;
+            Ok(RecordingEncoder::Gif(encoder))
+        } else if lower.ends_with(".mp4") {
+            let child = Command::new("ffmpeg")
+                .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba"])
+                .args(["-video_size", &format!("{}x{}", width, height), "-framerate", &fps.to_string(), "-i", "-"])
+                // yuv420p needs even dimensions
+                .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p", "-c:v", "libx264", path])
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("ffmpeg is required to record MP4 ({})", e))?;
+            Ok(RecordingEncoder::Mp4(child))
+        } else {
+            Err("Recording path must end in .gif or .mp4".to_string())
+        }
+    }
+
+    // `duration` is how long the frame stays on screen; MP4 uses the fixed rate
+    fn write(&mut self, frame: image::RgbaImage, duration: std::time::Duration) -> Result<(), String> {
+        match self {
+            RecordingEncoder::Gif(encoder) => {
+                let delay = image::Delay::from_saturating_duration(duration);
+                encoder.encode_frame(image::Frame::from_parts(frame, 0, 0, delay)).map_err(|e| e.to_string())
+            }
+            RecordingEncoder::Mp4(child) => {
+                use std::io::Write;
+                let stdin = child.stdin.as_mut().ok_or("ffmpeg input closed")?;
+                stdin.write_all(frame.as_raw()).map_err(|e| format!("ffmpeg stopped accepting frames: {}", e))
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            // The GIF trailer is written when the encoder drops
+            RecordingEncoder::Gif(encoder) => {
+                drop(encoder);
+                Ok(())
+            }
+            RecordingEncoder::Mp4(mut child) => {
+                drop(child.stdin.take());
+                let output = child.wait_with_output().map_err(|e| e.to_string())?;
+                if output.status.success() {
+                    Ok(())
+                } else {
+                    Err(format!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr).trim()))
+                }
+            }
+        }
+    }
+}
+
+// Writes frames as they arrive. Each GIF frame is held until the next one
+// comes so its delay matches the real time between captures.
+fn encode_recording(
+    mut encoder: RecordingEncoder,
+    fps: u32,
+    frames: std::sync::mpsc::Receiver<(image::RgbaImage, std::time::Instant)>,
+) -> Result<u32, String> {
+    let mut pending: Option<(image::RgbaImage, std::time::Instant)> = None;
+    let mut written = 0u32;
+    let mut size = None;
+    for (frame, captured_at) in frames {
+        if *size.get_or_insert(frame.dimensions()) != frame.dimensions() {
+            encoder.finish().ok();
+            return Err("Display layout changed during recording".to_string());
+        }
+        if let Some((previous, previous_at)) = pending.replace((frame, captured_at)) {
+            encoder.write(previous, captured_at - previous_at)?;
+            written += 1;
+        }
+    }
+    if let Some((last, _)) = pending {
+        encoder.write(last, std::time::Duration::from_secs(1) / fps)?;
+        written += 1;
+    }
+    encoder.finish()?;
+    Ok(written)
+}
+
+// Records `region` (or the whole desktop) to a .gif or .mp4 file until
+// stop_screen_record. MP4 needs ffmpeg on PATH.
+#[tauri::command(async)]
+fn start_screen_record(
+    app: tauri::AppHandle,
+    path: String,
+    fps: u32,
+    region: Option<ScreenRegion>,
+    recorder: tauri::State<'_, ScreenRecorder>,
+) -> Result<String, String> {
+    if !(1..=30).contains(&fps) {
+        return Err("fps must be between 1 and 30".to_string());
+    }
+    let mut active = recorder.active.lock().unwrap();
+    if active.is_some() {
+        return Err("A screen recording is already running".to_string());
+    }
+
+    // The first frame fixes the size and checks that capture works at all
+    let started_at = std::time::Instant::now();
+    let (first, _, _) = capture_area(&app, region)?;
+    let (width, height) = first.dimensions();
+    let encoder = RecordingEncoder::create(&path, width, height, fps)?;
+
+    let (sender, frames) = std::sync::mpsc::sync_channel(SCREEN_RECORD_QUEUE_FRAMES);
+    sender.send((first, started_at)).ok();
+    let encoder = std::thread::spawn(move || encode_recording(encoder, fps, frames));
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    let capture = std::thread::spawn(move || {
+        let interval = std::time::Duration::from_secs(1) / fps;
+        let mut next = started_at + interval;
+        while !stopped.load(Ordering::SeqCst) {
+            let now = std::time::Instant::now();
+            if next > now {
+                std::thread::sleep(next - now);
+            }
+            // After a slow capture, continue from now rather than bursting to catch up
+            next = (next + interval).max(std::time::Instant::now());
+            if stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            let captured_at = std::time::Instant::now();
+            let (frame, _, _) = capture_area(&app, region)?;
+            // The encoder hung up after an error; stop reports it
+            if sender.send((frame, captured_at)).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    });
+
+    *active = Some(ScreenRecording { path: path.clone(), stop, capture, encoder });
+    Ok(format!("Recording {}x{} at {} fps to {}", width, height, fps, path))
+}
+
+// Stops capturing and waits for the encoder to write the queued frames and
+// finish the file
+#[tauri::command(async)]
+fn stop_screen_record(recorder: tauri::State<'_, ScreenRecorder>) -> Result<String, String> {
+    let recording = recorder.active.lock().unwrap().take().ok_or("No screen recording is running")?;
+    finish_screen_recording(recording)
+}
+
+fn finish_screen_recording(recording: ScreenRecording) -> Result<String, String> {
+    recording.stop.store(true, Ordering::SeqCst);
+    let captured = recording.capture.join().map_err(|_| "Screen capture thread panicked".to_string())?;
+    let frames = recording.encoder.join().map_err(|_| "Screen recording encoder panicked".to_string())??;
+    match captured {
+        Ok(()) => Ok(format!("Saved {} frames to {}", frames, recording.path)),
+        Err(e) => Err(format!("Capture failed ({}); the {} frames before it were saved to {}", e, frames, recording.path)),
+    }
+}
+
+// Foreign (other application) window commands
+#[derive(serde::Serialize, serde::Deserialize)]
+struct WindowRect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+// Visible top-level windows with a title, topmost first
+#[cfg(target_os = "windows")]
+fn list_foreign_windows() -> Vec<(windows_sys::Win32::Foundation::HWND, String)> {
+    use windows_sys::Win32::Foundation::{BOOL, HWND, LPARAM};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{EnumWindows, GetWindowTextW, IsWindowVisible};
+
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows = &mut *(lparam as *mut Vec<(HWND, String)>);
+        if IsWindowVisible(hwnd) != 0 {
+            let mut buffer = [0u16; 512];
+            let len = GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
+            if len > 0 {
+                windows.push((hwnd, String::from_utf16_lossy(&buffer[..len as usize])));
+            }
+        }
+        1
+    }
+
+    let mut windows: Vec<(HWND, String)> = Vec::new();
+    unsafe {
+        EnumWindows(Some(collect), &mut windows as *mut _ as LPARAM);
+    }
+    windows
+}
+
+#[cfg(target_os = "windows")]
+fn find_foreign_window(title_substring: &str) -> Result<windows_sys::Win32::Foundation::HWND, String> {
+    let needle = title_substring.to_lowercase();
+    list_foreign_windows()
+        .into_iter()
+        .find(|(_, title)| title.to_lowercase().contains(&needle))
+        .map(|(hwnd, _)| hwnd)
+        .ok_or(format!("No window found matching: {}", title_substring))
+}
+
+// Escape text for use inside an AppleScript string literal
+#[cfg(target_os = "macos")]
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[tauri::command]
+fn get_window_rect(title_substring: String) -> Result<WindowRect, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::RECT;
+        use windows_sys::Win32::UI::WindowsAndMessaging::GetWindowRect;
+
+        let hwnd = find_foreign_window(&title_substring)?;
+        let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+        if unsafe { GetWindowRect(hwnd, &mut rect) } == 0 {
+            return Err("Failed to read window bounds".to_string());
+        }
+        Ok(WindowRect {
+            x: rect.left,
+            y: rect.top,
+            width: (rect.right - rect.left).max(0) as u32,
+            height: (rect.bottom - rect.top).max(0) as u32,
+        })
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let script = format!(
+            r#"tell application "System Events"
+                repeat with proc in (processes whose visible is true)
+                    repeat with win in windows of proc
+                        if name of win contains {} then
+                            set {{px, py}} to position of win
+                            set {{sw, sh}} to size of win
+                            return (px as text) & "," & (py as text) & "," & (sw as text) & "," & (sh as text)
+                        end if
+                    end repeat
+                end repeat
+            end tell
+            return """#,
+            applescript_string(&title_substring)
+        );
+        let output = Command::new("osascript")
+            .args(["-e", &script])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let values: Vec<i32> = stdout.trim().split(',').filter_map(|v| v.trim().parse().ok()).collect();
+        match values[..] {
+            [x, y, width, height] => Ok(WindowRect { x, y, width: width.max(0) as u32, height: height.max(0) as u32 }),
+            _ => Err(format!("No window found matching: {}", title_substring)),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // wmctrl -lG: <id> <desktop> <x> <y> <width> <height> <host> <title...>
+        let output = Command::new("wmctrl")
+            .arg("-lG")
+            .output()
+            .map_err(|e| format!("Failed to run wmctrl (is it installed?): {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+
+        let needle = title_substring.to_lowercase();
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        for line in stdout.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 8 || !fields[7..].join(" ").to_lowercase().contains(&needle) {
+                continue;
+            }
+            let parse = |i: usize| fields[i].parse::<i32>().map_err(|e| e.to_string());
+            return Ok(WindowRect {
+                x: parse(2)?,
+                y: parse(3)?,
+                width: parse(4)?.max(0) as u32,
+                height: parse(5)?.max(0) as u32,
+            });
+        }
+        Err(format!("No window found matching: {}", title_substring))
+    }
+}
+
+// Bounds use the same units get_window_rect returns (points on macOS).
+// Maximized windows are restored first, otherwise most window managers
+// ignore the new geometry.
+#[tauri::command]
+fn set_foreign_window_bounds(title_substring: String, x: i32, y: i32, width: u32, height: u32) -> Result<String, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::UI::WindowsAndMessaging::{IsIconic, IsZoomed, SetWindowPos, ShowWindow, SWP_NOACTIVATE, SWP_NOZORDER, SW_RESTORE};
+
+        let hwnd = find_foreign_window(&title_substring)?;
+        unsafe {
+            if IsZoomed(hwnd) != 0 || IsIconic(hwnd) != 0 {
+                ShowWindow(hwnd, SW_RESTORE);
+            }
+            if SetWindowPos(hwnd, std::ptr::null_mut(), x, y, width as i32, height as i32, SWP_NOZORDER | SWP_NOACTIVATE) == 0 {
+                return Err("Failed to move window (it may belong to an elevated process)".to_string());
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let script = format!(
+            r#"tell application "System Events"
+                repeat with proc in (processes whose visible is true)
+                    repeat with win in windows of proc
+                        if name of win contains {} then
+                            set position of win to {{{}, {}}}
+                            set size of win to {{{}, {}}}
+                            return "found"
+                        end if
+                    end repeat
+                end repeat
+            end tell
+            return """#,
+            applescript_string(&title_substring), x, y, width, height
+        );
+        let output = Command::new("osascript")
+            .args(["-e", &script])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+        if String::from_utf8_lossy(&output.stdout).trim() != "found" {
+            return Err(format!("No window found matching: {}", title_substring));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let wmctrl = |args: &[&str]| Command::new("wmctrl")
+            .arg("-r")
+            .arg(&title_substring)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run wmctrl (is it installed?): {}", e));
+
+        let unmaximized = wmctrl(&["-b", "remove,maximized_vert,maximized_horz"])?;
+        if !unmaximized.status.success() {
+            return Err(format!("No window found matching: {}", title_substring));
+        }
+        let moved = wmctrl(&["-e", &format!("0,{},{},{},{}", x, y, width, height)])?;
+        if !moved.status.success() {
+            return Err(String::from_utf8_lossy(&moved.stderr).to_string());
+        }
+    }
+
+    Ok(format!("Moved window to ({}, {}) with size {}x{}", x, y, width, height))
+}
+
+// One window of a layout preset. `region` is a named part of the display's
+// work area: "full", "left-half", "right-half", "top-half", "bottom-half",
+// "top-left", "top-right", "bottom-left", "bottom-right", "left-third",
+// "center-third" or "right-third". Otherwise `bounds` gives the rectangle in
+// physical pixels relative to the work area's top-left corner, so presets
+// keep working when displays move. display defaults to the primary one.
+#[derive(serde::Deserialize)]
+struct WindowPlacement {
+    title: String,
+    display: Option<u32>,
+    region: Option<String>,
+    bounds: Option<WindowRect>,
+}
+
+#[derive(serde::Serialize)]
+struct PlacementResult {
+    title: String,
+    placed: bool,
+    // Where the window went, or why it couldn't be placed
+    message: String,
+}
+
+// Fractions (x, y, width, height) of the work area
+fn named_region(region: &str) -> Option<(f64, f64, f64, f64)> {
+    const THIRD: f64 = 1.0 / 3.0;
+    Some(match region {
+        "full" => (0.0, 0.0, 1.0, 1.0),
+        "left-half" => (0.0, 0.0, 0.5, 1.0),
+        "right-half" => (0.5, 0.0, 0.5, 1.0),
+        "top-half" => (0.0, 0.0, 1.0, 0.5),
+        "bottom-half" => (0.0, 0.5, 1.0, 0.5),
+        "top-left" => (0.0, 0.0, 0.5, 0.5),
+        "top-right" => (0.5, 0.0, 0.5, 0.5),
+        "bottom-left" => (0.0, 0.5, 0.5, 0.5),
+        "bottom-right" => (0.5, 0.5, 0.5, 0.5),
+        "left-third" => (0.0, 0.0, THIRD, 1.0),
+        "center-third" => (THIRD, 0.0, THIRD, 1.0),
+        "right-third" => (2.0 * THIRD, 0.0, THIRD, 1.0),
+        _ => return None,
+    })
+}
+
+// Each placement is tried even if an earlier one failed
+#[tauri::command(async)]
+fn apply_layout_preset(app: tauri::AppHandle, preset: Vec<WindowPlacement>) -> Result<Vec<PlacementResult>, String> {
+    let displays = display_layout(&app)?;
+    let primary = displays.iter().position(|display| display.is_primary).unwrap_or(0) as u32;
+
+    Ok(preset.into_iter().map(|placement| {
+        let placed = (|| {
+            let display_id = placement.display.unwrap_or(primary);
+            let scale = displays.get(display_id as usize)
+                .map(|display| display.scale_factor)
+                .ok_or(format!("Display not found: {}", display_id))?;
+            let area = get_work_area(app.clone(), display_id)?;
+
+            let (x, y, width, height) = match (&placement.region, &placement.bounds) {
+                (Some(region), _) => {
+                    let (fx, fy, fw, fh) = named_region(&region.to_lowercase())
+                        .ok_or(format!("Unknown region: {}", region))?;
+                    let (w, h) = (area.width as f64, area.height as f64);
+                    (area.x + (fx * w).round() as i32, area.y + (fy * h).round() as i32, (fw * w).round() as u32, (fh * h).round() as u32)
+                }
+                (None, Some(bounds)) => (area.x + bounds.x, area.y + bounds.y, bounds.width, bounds.height),
+                (None, None) => return Err("Placement needs a region or bounds".to_string()),
+            };
+
+            // set_foreign_window_bounds works in points on macOS
+            #[cfg(target_os = "macos")]
+            let (x, y, width, height) = (
+                (x as f64 / scale).round() as i32,
+                (y as f64 / scale).round() as i32,
+                (width as f64 / scale).round() as u32,
+                (height as f64 / scale).round() as u32,
+            );
+            #[cfg(not(target_os = "macos"))]
+            let _ = scale;
+
+            set_foreign_window_bounds(placement.title.clone(), x, y, width, height)
+        })();
+
+        match placed {
+            Ok(message) => PlacementResult { title: placement.title, placed: true, message },
+            Err(message) => PlacementResult { title: placement.title, placed: false, message },
+        }
+    }).collect())
+}
+
+#[derive(serde::Serialize)]
+struct HoveredWindow {
+    title: String,
+    // Window class on Windows and X11, owning application name on macOS
+    class: String,
+    pid: u32,
+    bounds: WindowRect,
+}
+
+// Top-level window under the mouse pointer; errors over the bare desktop.
+// On macOS titles are empty unless the app has Screen Recording permission.
+#[tauri::command]
+fn window_under_cursor() -> Result<HoveredWindow, String> {
+    let nothing = || "No window under the cursor".to_string();
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::{POINT, RECT};
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            GetAncestor, GetClassNameW, GetCursorPos, GetShellWindow, GetWindowRect, GetWindowTextW,
+            GetWindowThreadProcessId, WindowFromPoint, GA_ROOT,
+        };
+
+        unsafe {
+            let mut point = POINT { x: 0, y: 0 };
+            if GetCursorPos(&mut point) == 0 {
+                return Err("Failed to read the cursor position".to_string());
+            }
+            // WindowFromPoint returns the deepest child; report its top-level window
+            let hwnd = GetAncestor(WindowFromPoint(point), GA_ROOT);
+            if hwnd.is_null() || hwnd == GetShellWindow() {
+                return Err(nothing());
+            }
+
+            let mut buffer = [0u16; 512];
+            let len = GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32).max(0) as usize;
+            let title = String::from_utf16_lossy(&buffer[..len]);
+            let len = GetClassNameW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32).max(0) as usize;
+            let class = String::from_utf16_lossy(&buffer[..len]);
+            // The desktop's icon layer sits in a top-level WorkerW/Progman window
+            if class == "WorkerW" || class == "Progman" {
+                return Err(nothing());
+            }
+
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd, &mut pid);
+            let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+            GetWindowRect(hwnd, &mut rect);
+            Ok(HoveredWindow {
+                title,
+                class,
+                pid,
+                bounds: WindowRect {
+                    x: rect.left,
+                    y: rect.top,
+                    width: (rect.right - rect.left).max(0) as u32,
+                    height: (rect.bottom - rect.top).max(0) as u32,
+                },
+            })
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // CGWindowListCopyWindowInfo lists on-screen windows front to back;
+        // layer 0 excludes the menu bar, dock and other system overlays
+        let enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+        let (x, y) = enigo.location().map_err(describe_enigo_error)?;
+        let script = format!(
+            r#"ObjC.import('CoreGraphics');
+            var windows = ObjC.deepUnwrap(ObjC.castRefToObject($.CGWindowListCopyWindowInfo($.kCGWindowListOptionOnScreenOnly | $.kCGWindowListExcludeDesktopElements, $.kCGNullWindowID)));
+            var hit = windows.find(function (w) {{
+                var b = w.kCGWindowBounds;
+                return w.kCGWindowLayer === 0 && {x} >= b.X && {y} >= b.Y && {x} < b.X + b.Width && {y} < b.Y + b.Height;
+            }});
+            hit ? JSON.stringify({{title: hit.kCGWindowName || '', class: hit.kCGWindowOwnerName || '', pid: hit.kCGWindowOwnerPID, x: hit.kCGWindowBounds.X, y: hit.kCGWindowBounds.Y, width: hit.kCGWindowBounds.Width, height: hit.kCGWindowBounds.Height}}) : ''"#,
+            x = x,
+            y = y
+        );
+        let output = Command::new("osascript")
+            .args(["-l", "JavaScript", "-e", &script])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        if stdout.trim().is_empty() {
+            return Err(nothing());
+        }
+        let hit: serde_json::Value = serde_json::from_str(stdout.trim()).map_err(|e| e.to_string())?;
+        let number = |key: &str| hit[key].as_f64().unwrap_or(0.0);
+        Ok(HoveredWindow {
+            title: hit["title"].as_str().unwrap_or_default().to_string(),
+            class: hit["class"].as_str().unwrap_or_default().to_string(),
+            pid: number("pid") as u32,
+            bounds: WindowRect {
+                x: number("x") as i32,
+                y: number("y") as i32,
+                width: number("width") as u32,
+                height: number("height") as u32,
+            },
+        })
+    }
+
+    // X11 only, via xdotool and xprop
+    #[cfg(target_os = "linux")]
+    {
+        let run = |program: &str, args: &[&str]| -> Result<String, String> {
+            let output = Command::new(program)
+                .args(args)
+                .output()
+                .map_err(|e| format!("Failed to run {} (is it installed?): {}", program, e))?;
+            if !output.status.success() {
+                return Err(nothing());
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+        // --shell output is KEY=value lines
+        let shell_value = |text: &str, key: &str| {
+            text.lines()
+                .find_map(|line| line.strip_prefix(&format!("{}=", key)))
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let location = run("xdotool", &["getmouselocation", "--shell"])?;
+        let window = shell_value(&location, "WINDOW");
+        let title = run("xdotool", &["getwindowname", &window])?;
+        let pid = run("xdotool", &["getwindowpid", &window])
+            .ok()
+            .and_then(|pid| pid.parse().ok())
+            .unwrap_or(0);
+        // WM_CLASS(STRING) = "instance", "Class"
+        let class = run("xprop", &["-id", &window, "WM_CLASS"])
+            .ok()
+            .and_then(|line| line.rsplit('"').nth(1).map(|class| class.to_string()))
+            .unwrap_or_default();
+        // Over the desktop xdotool reports the unnamed root window
+        if title.is_empty() && class.is_empty() {
+            return Err(nothing());
+        }
+        let geometry = run("xdotool", &["getwindowgeometry", "--shell", &window])?;
+        let parse = |key: &str| shell_value(&geometry, key).parse::<i64>().unwrap_or(0);
+
+        Ok(HoveredWindow {
+            title,
+            class,
+            pid,
+            bounds: WindowRect {
+                x: parse("X") as i32,
+                y: parse("Y") as i32,
+                width: parse("WIDTH").max(0) as u32,
+                height: parse("HEIGHT").max(0) as u32,
+            },
+        })
+    }
+}
+
+#[derive(serde::Serialize)]
+struct StackedWindow {
+    title: String,
+    pid: u32,
+    // 0 is the topmost window at the point
+    z_index: u32,
+    bounds: WindowRect,
+}
+
+// Every titled top-level window covering (x, y), topmost first, in the same
+// coordinates as window_under_cursor. Windows hidden on other virtual
+// desktops are left out.
+#[tauri::command]
+fn get_windows_at_point(x: i32, y: i32) -> Result<Vec<StackedWindow>, String> {
+    let contains = |rect: &WindowRect| {
+        x >= rect.x && y >= rect.y && x < rect.x + rect.width as i32 && y < rect.y + rect.height as i32
+    };
+    let mut stack: Vec<(String, u32, WindowRect)> = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::RECT;
+        use windows_sys::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED};
+        use windows_sys::Win32::UI::WindowsAndMessaging::{GetWindowRect, GetWindowThreadProcessId};
+
+        for (hwnd, title) in list_foreign_windows() {
+            unsafe {
+                // Cloaked: on another virtual desktop, or a suspended store app
+                let mut cloaked = 0u32;
+                DwmGetWindowAttribute(hwnd, DWMWA_CLOAKED as u32, &mut cloaked as *mut u32 as *mut _, 4);
+                if cloaked != 0 {
+                    continue;
+                }
+                let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+                if GetWindowRect(hwnd, &mut rect) == 0 {
+                    continue;
+                }
+                let mut pid = 0u32;
+                GetWindowThreadProcessId(hwnd, &mut pid);
+                stack.push((title, pid, WindowRect {
+                    x: rect.left,
+                    y: rect.top,
+                    width: (rect.right - rect.left).max(0) as u32,
+                    height: (rect.bottom - rect.top).max(0) as u32,
+                }));
+            }
+        }
+    }
+
+    // Same window list as window_under_cursor, already front to back
+    #[cfg(target_os = "macos")]
+    {
+        let script = r#"ObjC.import('CoreGraphics');
+            var windows = ObjC.deepUnwrap(ObjC.castRefToObject($.CGWindowListCopyWindowInfo($.kCGWindowListOptionOnScreenOnly | $.kCGWindowListExcludeDesktopElements, $.kCGNullWindowID)));
+            JSON.stringify(windows.filter(function (w) { return w.kCGWindowLayer === 0; }).map(function (w) {
+                var b = w.kCGWindowBounds;
+                return {title: w.kCGWindowName || w.kCGWindowOwnerName || '', pid: w.kCGWindowOwnerPID, x: b.X, y: b.Y, width: b.Width, height: b.Height};
+            }))"#;
+        let output = Command::new("osascript")
+            .args(["-l", "JavaScript", "-e", script])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+        let windows: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+        for window in windows {
+            let number = |key: &str| window[key].as_f64().unwrap_or(0.0);
+            stack.push((window["title"].as_str().unwrap_or_default().to_string(), number("pid") as u32, WindowRect {
+                x: number("x") as i32,
+                y: number("y") as i32,
+                width: number("width") as u32,
+                height: number("height") as u32,
+            }));
+        }
+    }
+
+    // X11 only: the window manager's stacking list runs bottom to top, and
+    // xdotool tells which of those windows are actually mapped
+    #[cfg(target_os = "linux")]
+    {
+        let run = |program: &str, args: &[&str]| -> Result<String, String> {
+            let output = Command::new(program)
+                .args(args)
+                .output()
+                .map_err(|e| format!("Failed to run {} (is it installed?): {}", program, e))?;
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+
+        // _NET_CLIENT_LIST_STACKING(WINDOW): window id # 0x1e00003, 0x2400007
+        let stacking = run("xprop", &["-root", "_NET_CLIENT_LIST_STACKING"])?;
+        let visible: std::collections::HashSet<String> = run("xdotool", &["search", "--onlyvisible", "--name", ""])?
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let ids = stacking.split_once('#').map(|(_, ids)| ids).unwrap_or_default();
+        for id in ids.split(',').rev() {
+            let Ok(id) = u64::from_str_radix(id.trim().trim_start_matches("0x"), 16) else { continue };
+            let window = id.to_string();
+            if !visible.contains(&window) {
+                continue;
+            }
+            let geometry = run("xdotool", &["getwindowgeometry", "--shell", &window])?;
+            let parse = |key: &str| {
+                geometry.lines()
+                    .find_map(|line| line.strip_prefix(&format!("{}=", key)))
+                    .and_then(|value| value.parse::<i64>().ok())
+                    .unwrap_or(0)
+            };
+            let title = run("xdotool", &["getwindowname", &window])?;
+            if title.is_empty() {
+                continue;
+            }
+            let pid = run("xdotool", &["getwindowpid", &window])?.parse().unwrap_or(0);
+            stack.push((title, pid, WindowRect {
+                x: parse("X") as i32,
+                y: parse("Y") as i32,
+                width: parse("WIDTH").max(0) as u32,
+                height: parse("HEIGHT").max(0) as u32,
+            }));
+        }
+    }
+
+    Ok(stack.into_iter()
+        .filter(|(_, _, bounds)| contains(bounds))
+        .enumerate()
+        .map(|(z_index, (title, pid, bounds))| StackedWindow { title, pid, z_index: z_index as u32, bounds })
+        .collect())
+}
+
+#[derive(serde::Serialize)]
+struct ActiveProcess {
+    pid: u32,
+    name: String,
+    // None when the OS won't reveal it (e.g. elevated processes on Windows)
+    exe_path: Option<String>,
+}
+
+// Process owning the foreground window. Unlike the window title, the
+// executable path stays the same whatever document the app has open.
+#[tauri::command]
+fn get_active_process() -> Result<ActiveProcess, String> {
+    #[cfg(target_os = "windows")]
+    let pid = unsafe {
+        use windows_sys::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_null() {
+            return Err("No foreground window".to_string());
+        }
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        pid
+    };
+
+    #[cfg(target_os = "macos")]
+    let pid = {
+        let output = Command::new("osascript")
+            .args(["-e", "tell application \"System Events\" to get unix id of first process whose frontmost is true"])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+        String::from_utf8_lossy(&output.stdout).trim().parse::<u32>().map_err(|e| e.to_string())?
+    };
+
+    // X11 only, via xdotool
+    #[cfg(target_os = "linux")]
+    let pid = {
+        let output = Command::new("xdotool")
+            .args(["getactivewindow", "getwindowpid"])
+            .output()
+            .map_err(|e| format!("Failed to run xdotool (is it installed?): {}", e))?;
+        if !output.status.success() {
+            return Err("No foreground window".to_string());
+        }
+        String::from_utf8_lossy(&output.stdout).trim().parse::<u32>().map_err(|e| e.to_string())?
+    };
+
+    let mut system = sysinfo::System::new();
+    let sys_pid = sysinfo::Pid::from_u32(pid);
+    system.refresh_processes_specifics(
+        sysinfo::ProcessesToUpdate::Some(&[sys_pid]),
+        false,
+        sysinfo::ProcessRefreshKind::nothing().with_exe(sysinfo::UpdateKind::Always),
+    );
+    let process = system.process(sys_pid).ok_or(format!("Process {} not found", pid))?;
+    Ok(ActiveProcess {
+        pid,
+        name: process.name().to_string_lossy().to_string(),
+        exe_path: process.exe().map(|path| path.to_string_lossy().to_string()),
+    })
+}
+
+// Dragging files onto other apps
+// A real OS drag and drop, driven by synthetic cursor moves, so drop zones
+// that only take drag and drop (web page upload areas included) accept it.
+
+// Moves the cursor to (x, y) in small steps, then wiggles it over the target
+// for a moment; drop targets only decide whether they accept the files while
+// the drag moves over them.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn drag_cursor_to(enigo: &mut Enigo, x: i32, y: i32, automation: &Automation, token: u64) -> Result<(), String> {
+    const STEPS: i32 = 20;
+    let (start_x, start_y) = enigo.location().map_err(describe_enigo_error)?;
+    for step in 1..=STEPS {
+        let step_x = start_x + (x - start_x) * step / STEPS;
+        let step_y = start_y + (y - start_y) * step / STEPS;
+        enigo.move_mouse(step_x, step_y, Coordinate::Abs).map_err(describe_enigo_error)?;
+        automation.sleep(token, std::time::Duration::from_millis(20))?;
+    }
+    for offset in [1, 0, -1, 0, 1, 0] {
+        enigo.move_mouse(x + offset, y, Coordinate::Abs).map_err(describe_enigo_error)?;
+        automation.sleep(token, std::time::Duration::from_millis(50))?;
+    }
+    Ok(())
+}
+
+// Minimal COM IDropSource. The drag isn't tied to a held mouse button:
+// it drops once `arrived` is set and cancels once `cancelled` is set. OLE
+// holds references only for the length of DoDragDrop, which the owner
+// outlives, so reference counting is a no-op.
+#[cfg(target_os = "windows")]
+#[repr(C)]
+struct DropSource {
+    vtable: *const DropSourceVtable,
+    arrived: Arc<AtomicBool>,
+    cancelled: Arc<AtomicBool>,
+}
+
+#[cfg(target_os = "windows")]
+#[repr(C)]
+struct DropSourceVtable {
+    query_interface: unsafe extern "system" fn(*mut DropSource, *const windows_sys::core::GUID, *mut *mut std::ffi::c_void) -> windows_sys::core::HRESULT,
+    add_ref: unsafe extern "system" fn(*mut DropSource) -> u32,
+    release: unsafe extern "system" fn(*mut DropSource) -> u32,
+    query_continue_drag: unsafe extern "system" fn(*mut DropSource, windows_sys::Win32::Foundation::BOOL, u32) -> windows_sys::core::HRESULT,
+    give_feedback: unsafe extern "system" fn(*mut DropSource, u32) -> windows_sys::core::HRESULT,
+}
+
+#[cfg(target_os = "windows")]
+fn same_guid(a: &windows_sys::core::GUID, b: &windows_sys::core::GUID) -> bool {
+    (a.data1, a.data2, a.data3, a.data4) == (b.data1, b.data2, b.data3, b.data4)
+}
+
+#[cfg(target_os = "windows")]
+const DROP_SOURCE_VTABLE: DropSourceVtable = {
+    use windows_sys::core::{GUID, HRESULT};
+    use windows_sys::Win32::Foundation::{BOOL, DRAGDROP_S_CANCEL, DRAGDROP_S_DROP, DRAGDROP_S_USEDEFAULTCURSORS, E_NOINTERFACE, S_OK};
+
+    const IID_IUNKNOWN: GUID = GUID::from_u128(0x00000000_0000_0000_c000_000000000046);
+    const IID_IDROPSOURCE: GUID = GUID::from_u128(0x00000121_0000_0000_c000_000000000046);
+
+    unsafe extern "system" fn query_interface(this: *mut DropSource, riid: *const GUID, object: *mut *mut std::ffi::c_void) -> HRESULT {
+        if same_guid(&*riid, &IID_IUNKNOWN) || same_guid(&*riid, &IID_IDROPSOURCE) {
+            *object = this as *mut std::ffi::c_void;
+            S_OK
+        } else {
+            *object = std::ptr::null_mut();
+            E_NOINTERFACE
+        }
+    }
+    unsafe extern "system" fn add_ref(_: *mut DropSource) -> u32 {
+        1
+    }
+    unsafe extern "system" fn release(_: *mut DropSource) -> u32 {
+        1
+    }
+    unsafe extern "system" fn query_continue_drag(this: *mut DropSource, escape_pressed: BOOL, _key_state: u32) -> HRESULT {
+        let source = &*this;
+        if escape_pressed != 0 || source.cancelled.load(Ordering::SeqCst) {
+            DRAGDROP_S_CANCEL
+        } else if source.arrived.load(Ordering::SeqCst) {
+            DRAGDROP_S_DROP
+        } else {
+            S_OK
+        }
+    }
+    unsafe extern "system" fn give_feedback(_: *mut DropSource, _effect: u32) -> HRESULT {
+        DRAGDROP_S_USEDEFAULTCURSORS
+    }
+
+    DropSourceVtable { query_interface, add_ref, release, query_continue_drag, give_feedback }
+};
+
+// Shell data object for the files, offering CF_HDROP and the shell's own
+// formats, as Explorer does when you drag files from it
+#[cfg(target_os = "windows")]
+unsafe fn shell_data_object(files: &[std::path::PathBuf]) -> Result<*mut std::ffi::c_void, String> {
+    use windows_sys::core::GUID;
+    use windows_sys::Win32::UI::Shell::{ILCreateFromPathW, ILFree, SHCreateDataObject};
+
+    const IID_IDATAOBJECT: GUID = GUID::from_u128(0x0000010e_0000_0000_c000_000000000046);
+
+    let pidls: Vec<_> = files
+        .iter()
+        .map(|file| {
+            // canonicalize gives \\?\ paths, which the shell can't parse
+            let path = file.to_string_lossy();
+            ILCreateFromPathW(wide_null(path.strip_prefix(r"\\?\").unwrap_or(&path)).as_ptr())
+        })
+        .collect();
+    let mut object = std::ptr::null_mut();
+    let result = if pidls.iter().any(|pidl| pidl.is_null()) {
+        Err("Failed to resolve the files to drag".to_string())
+    } else {
+        // Absolute item IDs are children of the desktop, the null folder
+        let hr = SHCreateDataObject(
+            std::ptr::null(),
+            pidls.len() as u32,
+            pidls.as_ptr() as *const *const _,
+            std::ptr::null_mut(),
+            &IID_IDATAOBJECT,
+            &mut object,
+        );
+        if hr < 0 {
+            Err(format!("Failed to create the drag data: {}", std::io::Error::from_raw_os_error(hr)))
+        } else {
+            Ok(object)
+        }
+    };
+    for pidl in pidls.into_iter().filter(|pidl| !pidl.is_null()) {
+        ILFree(pidl);
+    }
+    result
+}
+
+// Calls IUnknown::Release on a COM object
+#[cfg(target_os = "windows")]
+unsafe fn release_com_object(object: *mut std::ffi::c_void) {
+    let vtable = *(object as *const *const [usize; 3]);
+    let release: unsafe extern "system" fn(*mut std::ffi::c_void) -> u32 = std::mem::transmute((*vtable)[2]);
+    release(object);
+}
+
+// Drags files from the cursor's current position and drops them at (x, y),
+// screen coordinates, as if the user dragged them there. Windows runs an OLE
+// drag with a shell data object; Linux drags from a dragon-drop window
+// (https://github.com/mwh/dragon), which must be installed.
+#[tauri::command(async)]
+fn drag_files_to(paths: Vec<String>, x: i32, y: i32, automation: tauri::State<'_, Automation>) -> Result<String, String> {
+    if paths.is_empty() {
+        return Err("No files to drop".to_string());
+    }
+    let files = paths
+        .iter()
+        .map(|path| std::fs::canonicalize(path).map_err(|e| format!("{}: {}", path, e)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let token = automation.token();
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::DRAGDROP_S_DROP;
+        use windows_sys::Win32::System::Ole::{DoDragDrop, OleInitialize, OleUninitialize, DROPEFFECT_COPY, DROPEFFECT_LINK, DROPEFFECT_NONE};
+
+        let count = files.len();
+        let started = Arc::new(AtomicBool::new(false));
+        let arrived = Arc::new(AtomicBool::new(false));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        // DoDragDrop runs a modal loop, so it gets its own OLE thread while
+        // this one moves the cursor
+        let (drag_started, drag_arrived, drag_cancelled) = (started.clone(), arrived.clone(), cancelled.clone());
+        let drag = std::thread::spawn(move || unsafe {
+            if OleInitialize(std::ptr::null()) < 0 {
+                return Err("Failed to initialize OLE".to_string());
+            }
+            let result = shell_data_object(&files).map(|data| {
+                let mut source = DropSource { vtable: &DROP_SOURCE_VTABLE, arrived: drag_arrived, cancelled: drag_cancelled };
+                let mut effect = DROPEFFECT_NONE;
+                drag_started.store(true, Ordering::SeqCst);
+                let hr = DoDragDrop(data, &mut source as *mut DropSource as *mut _, DROPEFFECT_COPY | DROPEFFECT_LINK, &mut effect);
+                release_com_object(data);
+                (hr, effect)
+            });
+            OleUninitialize();
+            result
+        });
+
+        let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+        while !started.load(Ordering::SeqCst) && !drag.is_finished() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        if drag.is_finished() {
+            // Setting up the drag failed
+            drag.join().map_err(|_| "Drag thread panicked".to_string())??;
+            return Err("The drag ended before reaching the target".to_string());
+        }
+        let moved = drag_cursor_to(&mut enigo, x, y, &automation, token);
+        match &moved {
+            Ok(()) => arrived.store(true, Ordering::SeqCst),
+            Err(_) => cancelled.store(true, Ordering::SeqCst),
+        }
+
+        // The drag loop only asks the source again on input, so keep nudging
+        // the cursor until it finishes
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        let mut offset = 1;
+        while !drag.is_finished() && std::time::Instant::now() < deadline {
+            enigo.move_mouse(x + offset, y, Coordinate::Abs).ok();
+            offset = 1 - offset;
+            std::thread::sleep(std::time::Duration::from_millis(25));
+        }
+        if !drag.is_finished() {
+            return Err("The drag didn't finish; press Escape to end it".to_string());
+        }
+        let (hr, effect) = drag.join().map_err(|_| "Drag thread panicked".to_string())??;
+        moved?;
+        if hr != DRAGDROP_S_DROP {
+            Err("The drag was cancelled".to_string())
+        } else if effect == DROPEFFECT_NONE {
+            Err(format!("The target at ({}, {}) didn't accept the files", x, y))
+        } else {
+            Ok(format!("Dropped {} files at ({}, {})", count, x, y))
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // --all drags every file at once; --and-exit quits after a drop, so
+        // a still-running dragon means the target refused the files
+        // Packaged as dragon-drop on Debian and Ubuntu, dragon upstream
+        let spawn_dragon = |program: &str| Command::new(program).args(["--all", "--and-exit", "--on-top"]).args(&files).spawn();
+        let mut dragon = spawn_dragon("dragon-drop")
+            .or_else(|_| spawn_dragon("dragon"))
+            .map_err(|e| format!("Failed to run dragon-drop (is it installed?): {}", e))?;
+        let result = (|| {
+            let mut geometry = None;
+            for _ in 0..30 {
+                automation.sleep(token, std::time::Duration::from_millis(100))?;
+                let search = Command::new("xdotool")
+                    .args(["search", "--onlyvisible", "--pid", &dragon.id().to_string(), "getwindowgeometry", "--shell"])
+                    .output()
+                    .map_err(|e| format!("Failed to run xdotool (is it installed?): {}", e))?;
+                let text = String::from_utf8_lossy(&search.stdout).to_string();
+                let value = |name: &str| text.lines().find_map(|line| line.strip_prefix(name)?.parse::<i32>().ok());
+                if let (Some(left), Some(top), Some(width), Some(height)) = (value("X="), value("Y="), value("WIDTH="), value("HEIGHT=")) {
+                    geometry = Some((left + width / 2, top + height / 2));
+                    break;
+                }
+            }
+            let (grab_x, grab_y) = geometry.ok_or("The dragon-drop window didn't appear")?;
+
+            let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+            enigo.move_mouse(grab_x, grab_y, Coordinate::Abs).map_err(describe_enigo_error)?;
+            automation.sleep(token, std::time::Duration::from_millis(100))?;
+            enigo.button(Button::Left, Direction::Press).map_err(describe_enigo_error)?;
+            let moved = drag_cursor_to(&mut enigo, x, y, &automation, token);
+            enigo.button(Button::Left, Direction::Release).map_err(describe_enigo_error)?;
+            moved?;
+
+            for _ in 0..10 {
+                if dragon.try_wait().map_err(|e| e.to_string())?.is_some() {
+                    return Ok(format!("Dropped {} files at ({}, {})", files.len(), x, y));
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            Err(format!("The target at ({}, {}) didn't accept the files", x, y))
+        })();
+        dragon.kill().ok();
+        dragon.wait().ok();
+        result
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let _ = (files, x, y, token);
+        Err("Dragging files onto other apps is only supported on Windows and Linux".to_string())
+    }
+}
+
+// Bring another application's window to the foreground
+#[cfg(not(target_os = "windows"))]
+fn focus_foreign_window(title_substring: &str) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let output = Command::new("osascript")
+        .args(["-e", &format!(
+            r#"tell application "System Events"
+                repeat with proc in (processes whose visible is true)
+                    repeat with win in windows of proc
+                        if name of win contains {} then
+                            set frontmost of proc to true
+                            perform action "AXRaise" of win
+                            return "found"
+                        end if
+                    end repeat
+                end repeat
+            end tell
+            return """#,
+            applescript_string(title_substring)
+        )])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    #[cfg(target_os = "linux")]
+    let output = Command::new("wmctrl")
+        .args(["-a", title_substring])
+        .output()
+        .map_err(|e| format!("Failed to run wmctrl (is it installed?): {}", e))?;
+
+    let found = output.status.success()
+        && (cfg!(target_os = "linux") || String::from_utf8_lossy(&output.stdout).trim() == "found");
+    if found {
+        Ok(())
+    } else {
+        Err(format!("No window found matching: {}", title_substring))
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn virtual_key(key: &str) -> Result<u16, String> {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::*;
+
+    let vk = match key.to_lowercase().as_str() {
+        "enter" | "return" => VK_RETURN,
+        "tab" => VK_TAB,
+        "escape" | "esc" => VK_ESCAPE,
+        "backspace" => VK_BACK,
+        "delete" | "del" => VK_DELETE,
+        "up" | "uparrow" => VK_UP,
+        "down" | "downarrow" => VK_DOWN,
+        "left" | "leftarrow" => VK_LEFT,
+        "right" | "rightarrow" => VK_RIGHT,
+        "home" => VK_HOME,
+        "end" => VK_END,
+        "pageup" => VK_PRIOR,
+        "pagedown" => VK_NEXT,
+        "space" => VK_SPACE,
+        "shift" => VK_SHIFT,
+        "control" | "ctrl" => VK_CONTROL,
+        "alt" => VK_MENU,
+        "meta" | "windows" | "cmd" | "command" => VK_LWIN,
+        single if single.chars().count() == 1 => {
+            let c = single.chars().next().unwrap_or_default();
+            let scan = if (c as u32) <= 0xFFFF { unsafe { VkKeyScanW(c as u16) } } else { -1 };
+            if scan == -1 {
+                return Err(format!("Key has no virtual key code on this layout: {}", key));
+            }
+            (scan & 0xFF) as u16
+        }
+        _ => return Err(format!("Unsupported key: {}", key)),
+    };
+    Ok(vk)
+}
+
+// On Windows the keys are posted straight to the target window's message
+// queue, so the user's foreground window keeps focus. Apps that read the
+// live keyboard state (GetKeyState) will not see modifiers sent this way.
+// macOS and Linux have no equivalent, so the window is focused first and
+// the keys are sent as normal synthetic input.
+#[tauri::command]
+fn send_keys_to_window(title_substring: String, keys: Vec<String>, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{MapVirtualKeyW, MAPVK_VK_TO_VSC};
+        use windows_sys::Win32::UI::WindowsAndMessaging::{PostMessageW, WM_KEYDOWN, WM_KEYUP};
+
+        let hwnd = find_foreign_window(&title_substring)?;
+        let codes = keys.iter().map(|k| virtual_key(k)).collect::<Result<Vec<u16>, String>>()?;
+
+        let post = |vk: u16, down: bool| -> Result<(), String> {
+            let scan = unsafe { MapVirtualKeyW(vk as u32, MAPVK_VK_TO_VSC) } as isize;
+            let (message, lparam) = if down {
+                (WM_KEYDOWN, 1 | (scan << 16))
+            } else {
+                (WM_KEYUP, 1 | (scan << 16) | (1 << 30) | (1 << 31))
+            };
+            if unsafe { PostMessageW(hwnd, message, vk as usize, lparam) } == 0 {
+                return Err("Failed to post key message to window".to_string());
+            }
+            Ok(())
+        };
+
+        for vk in &codes {
+            limiter.wait();
+            post(*vk, true)?;
+        }
+        for vk in codes.iter().rev() {
+            limiter.wait();
+            post(*vk, false)?;
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        focus_foreign_window(&title_substring)?;
+        let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+        press_shortcut(&mut enigo, &keys, &limiter)?;
+    }
+
+    Ok(format!("Sent {} to window matching: {}", keys.join("+"), title_substring))
+}
+
+// Types into the window of one specific process, so a same-titled window
+// from another instance can't receive the text. Like send_keys_to_window,
+// Windows posts the characters without changing focus (to the control that
+// has focus inside that window); macOS and Linux focus the window first.
+#[tauri::command]
+fn send_text_to_pid(pid: u32, text: String, limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    let no_window = || format!("Process {} has no visible window", pid);
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::UI::WindowsAndMessaging::{GetGUIThreadInfo, GetWindowThreadProcessId, PostMessageW, GUITHREADINFO, WM_CHAR};
+
+        // list_foreign_windows is topmost first, so this is the frontmost one
+        let (hwnd, thread) = list_foreign_windows()
+            .into_iter()
+            .find_map(|(hwnd, _)| {
+                let mut owner = 0u32;
+                let thread = unsafe { GetWindowThreadProcessId(hwnd, &mut owner) };
+                (owner == pid).then_some((hwnd, thread))
+            })
+            .ok_or_else(no_window)?;
+
+        let mut gui: GUITHREADINFO = unsafe { std::mem::zeroed() };
+        gui.cbSize = std::mem::size_of::<GUITHREADINFO>() as u32;
+        let target = if unsafe { GetGUIThreadInfo(thread, &mut gui) } != 0 && !gui.hwndFocus.is_null() {
+            gui.hwndFocus
+        } else {
+            hwnd
+        };
+
+        for unit in text.encode_utf16() {
+            limiter.wait();
+            if unsafe { PostMessageW(target, WM_CHAR, unit as usize, 1) } == 0 {
+                return Err("Failed to post text to window".to_string());
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let script = format!(
+            r#"tell application "System Events"
+                set matches to (processes whose unix id is {} and visible is true)
+                if (count of matches) is 0 then return ""
+                set proc to item 1 of matches
+                if (count of windows of proc) is 0 then return ""
+                set frontmost of proc to true
+                perform action "AXRaise" of window 1 of proc
+                return "found"
+            end tell"#,
+            pid
+        );
+        let output = Command::new("osascript")
+            .args(["-e", &script])
+            .output()
+            .map_err(|e| e.to_string())?;
+        // Fails without Accessibility access, for example
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+        if String::from_utf8_lossy(&output.stdout).trim() != "found" {
+            return Err(no_window());
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // wmctrl -lp: <id> <desktop> <pid> <host> <title...>
+        let output = Command::new("wmctrl")
+            .arg("-lp")
+            .output()
+            .map_err(|e| format!("Failed to run wmctrl (is it installed?): {}", e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let window_id = stdout.lines()
+            .map(|line| line.split_whitespace().collect::<Vec<&str>>())
+            .find(|fields| fields.len() >= 3 && fields[2].parse::<u32>() == Ok(pid))
+            .map(|fields| fields[0].to_string())
+            .ok_or_else(no_window)?;
+
+        let activated = Command::new("wmctrl")
+            .args(["-i", "-a", &window_id])
+            .status()
+            .map_err(|e| e.to_string())?;
+        if !activated.success() {
+            return Err(format!("Failed to focus the window of process {}", pid));
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // Give the window manager a moment to hand over keyboard focus
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+        limiter.wait();
+        enigo.text(&text).map_err(describe_enigo_error)?;
+    }
+
+    Ok(format!("Sent {} characters to process {}", text.chars().count(), pid))
+}
+
+// Installed application commands
+#[derive(serde::Serialize)]
+struct InstalledApp {
+    name: String,
+    // What to launch: a Start Menu shortcut (.lnk) on Windows, an .app
+    // bundle on macOS, or the desktop entry's Exec command line on Linux
+    path: String,
+}
+
+// Files with the given extension under `dir`, without descending into matches
+// (.app bundles are directories)
+fn find_with_extension(dir: &std::path::Path, extension: &str, depth: u32, found: &mut Vec<std::path::PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(extension)) {
+            found.push(path);
+        } else if depth > 0 && path.is_dir() {
+            find_with_extension(&path, extension, depth - 1, found);
+        }
+    }
+}
+
+#[tauri::command(async)]
+fn list_installed_apps() -> Vec<InstalledApp> {
+    let mut apps = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        let mut shortcuts = Vec::new();
+        for root in ["ProgramData", "APPDATA"] {
+            if let Ok(base) = std::env::var(root) {
+                let dir = std::path::Path::new(&base).join("Microsoft\\Windows\\Start Menu\\Programs");
+                find_with_extension(&dir, "lnk", 3, &mut shortcuts);
+            }
+        }
+        for shortcut in shortcuts {
+            let Some(name) = shortcut.file_stem().map(|s| s.to_string_lossy().to_string()) else { continue };
+            // Skip the uninstaller and readme links most installers add
+            let lower = name.to_lowercase();
+            if lower.contains("uninstall") || lower.contains("readme") {
+                continue;
+            }
+            apps.push(InstalledApp { name, path: shortcut.to_string_lossy().to_string() });
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let home = std::env::var("HOME").unwrap_or_default();
+        let mut bundles = Vec::new();
+        for dir in ["/Applications".to_string(), "/System/Applications".to_string(), format!("{}/Applications", home)] {
+            find_with_extension(std::path::Path::new(&dir), "app", 1, &mut bundles);
+        }
+        for bundle in bundles {
+            if let Some(name) = bundle.file_stem().map(|s| s.to_string_lossy().to_string()) {
+                apps.push(InstalledApp { name, path: bundle.to_string_lossy().to_string() });
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let home = std::env::var("HOME").unwrap_or_default();
+        let mut entries = Vec::new();
+        for dir in [
+            "/usr/share/applications".to_string(),
+            "/usr/local/share/applications".to_string(),
+            "/var/lib/flatpak/exports/share/applications".to_string(),
+            format!("{}/.local/share/applications", home),
+        ] {
+            find_with_extension(std::path::Path::new(&dir), "desktop", 1, &mut entries);
+        }
+
+        for entry in entries {
+            let Ok(contents) = std::fs::read_to_string(&entry) else { continue };
+            let (mut name, mut exec, mut hidden) = (None, None, false);
+            // Only the main [Desktop Entry] group, not [Desktop Action ...]
+            for line in contents.lines().skip_while(|line| line.trim() != "[Desktop Entry]").skip(1) {
+                if line.starts_with('[') {
+                    break;
+                }
+                match line.split_once('=') {
+                    Some(("Name", value)) => name = Some(value.trim().to_string()),
+                    Some(("Exec", value)) => exec = Some(value.trim().to_string()),
+                    Some(("NoDisplay" | "Hidden", value)) => hidden |= value.trim() == "true",
+                    _ => {}
+                }
+            }
+            if let (Some(name), Some(exec), false) = (name, exec, hidden) {
+                // Drop field codes like %U and %f that the launcher would fill in
+                let path = exec.split_whitespace().filter(|arg| !arg.starts_with('%')).collect::<Vec<_>>().join(" ");
+                apps.push(InstalledApp { name, path });
+            }
+        }
+    }
+
+    // The same app often appears in both the system and user locations
+    apps.sort_by_key(|app| app.name.to_lowercase());
+    apps.dedup_by(|a, b| a.name.eq_ignore_ascii_case(&b.name));
+    apps
+}
+
+// How well `name` matches `query` (both lowercase), higher is better:
+// 4 exact, 3 prefix, 2 contains every query word, 1 letters appear in order
+fn app_match_score(name: &str, query: &str) -> u8 {
+    if name == query {
+        4
+    } else if name.starts_with(query) {
+        3
+    } else if query.split_whitespace().all(|word| name.contains(word)) {
+        2
+    } else {
+        let mut letters = name.chars();
+        let in_order = query.chars().filter(|c| !c.is_whitespace()).all(|c| letters.any(|n| n == c));
+        if in_order { 1 } else { 0 }
+    }
+}
+
+#[tauri::command(async)]
+fn launch_app(name: String) -> Result<String, String> {
+    let query = name.trim().to_lowercase();
+    if query.is_empty() {
+        return Err("App name is empty".to_string());
+    }
+
+    let apps = list_installed_apps();
+    let scored: Vec<(u8, &InstalledApp)> = apps.iter()
+        .map(|app| (app_match_score(&app.name.to_lowercase(), &query), app))
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    let best = scored.iter().map(|(score, _)| *score).max()
+        .ok_or(format!("No installed app matches: {}", name))?;
+    let candidates: Vec<&InstalledApp> = scored.iter()
+        .filter(|(score, _)| *score == best)
+        .map(|(_, app)| *app)
+        .collect();
+
+    if candidates.len() > 1 {
+        let names: Vec<&str> = candidates.iter().take(5).map(|app| app.name.as_str()).collect();
+        return Err(format!("'{}' matches several apps: {}", name, names.join(", ")));
+    }
+    let app = candidates[0];
+
+    #[cfg(target_os = "windows")]
+    let spawned = Command::new("cmd").args(["/C", "start", "", &app.path]).spawn();
+
+    #[cfg(target_os = "macos")]
+    let spawned = Command::new("open").arg(&app.path).spawn();
+
+    // Exec lines are command lines, possibly with quoted arguments
+    #[cfg(target_os = "linux")]
+    let spawned = Command::new("sh").args(["-c", &app.path]).spawn();
+
+    let mut child = spawned.map_err(|e| format!("Failed to launch {}: {}", app.name, e))?;
+    // Reap the launcher in the background so it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+
+    Ok(format!("Launched {}", app.name))
+}
+
+// Batched action commands
+// One step of run_actions. Buttons and keys use the same names as the mouse
+// and keyboard commands; Wait is a pause in milliseconds.
+#[derive(serde::Deserialize)]
+enum Action {
+    MouseMove { x: i32, y: i32 },
+    MouseClick(String),
+    MouseDrag { from_x: i32, from_y: i32, to_x: i32, to_y: i32 },
+    KeyPress(String),
+    Type(String),
+    Wait(u64),
+    Shell(String),
+}
+
+#[derive(serde::Serialize)]
+struct ActionResult {
+    success: bool,
+    // The command's output on success, the error otherwise
+    message: String,
+}
+
+// Runs a whole scripted flow in one invoke instead of one round-trip per step.
+// Stops after the first failure unless continue_on_error is set.
+#[tauri::command(async)]
+fn run_actions(
+    actions: Vec<Action>,
+    continue_on_error: Option<bool>,
+    limiter: tauri::State<'_, InputRateLimiter>,
+    config: tauri::State<'_, ShellConfig>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<Vec<ActionResult>, String> {
+    let token = automation.token();
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    let mut results = Vec::new();
+
+    for action in &actions {
+        let result = automation.check(token).and_then(|_| run_action(&mut enigo, action, &limiter, &config, &automation, token));
+        let failed = result.is_err();
+        results.push(match result {
+            Ok(message) => ActionResult { success: true, message },
+            Err(message) => ActionResult { success: false, message },
+        });
+        // continue_on_error doesn't apply to a cancel
+        if failed && (!continue_on_error.unwrap_or(false) || automation.check(token).is_err()) {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+fn run_action(
+    enigo: &mut Enigo,
+    action: &Action,
+    limiter: &InputRateLimiter,
+    config: &ShellConfig,
+    automation: &Automation,
+    token: u64,
+) -> Result<String, String> {
+    match action {
+        Action::MouseMove { x, y } => {
+            limiter.wait();
+            enigo.move_mouse(*x, *y, Coordinate::Abs).map_err(describe_enigo_error)?;
+            Ok(format!("Moved mouse to ({}, {})", x, y))
+        }
+        Action::MouseClick(button) => {
+            let mouse_button = parse_button(button)?;
+            limiter.wait();
+            enigo.button(mouse_button, Direction::Click).map_err(describe_enigo_error)?;
+            Ok(format!("Clicked {} mouse button", button))
+        }
+        Action::MouseDrag { from_x, from_y, to_x, to_y } => {
+            limiter.wait();
+            enigo.move_mouse(*from_x, *from_y, Coordinate::Abs).map_err(describe_enigo_error)?;
+            limiter.wait();
+            enigo.button(Button::Left, Direction::Press).map_err(describe_enigo_error)?;
+            limiter.wait();
+            let moved = enigo.move_mouse(*to_x, *to_y, Coordinate::Abs);
+            // Release even if the move failed so the button isn't left held
+            limiter.wait();
+            enigo.button(Button::Left, Direction::Release).map_err(describe_enigo_error)?;
+            moved.map_err(describe_enigo_error)?;
+            Ok(format!("Dragged from ({}, {}) to ({}, {})", from_x, from_y, to_x, to_y))
+        }
+        Action::KeyPress(key) => {
+            let key_code = parse_key_or_char(key)?;
+            limiter.wait();
+            enigo.key(key_code, Direction::Click).map_err(describe_enigo_error)?;
+            Ok(format!("Pressed key: {}", key))
+        }
+        Action::Type(text) => {
+            limiter.wait();
+            enigo.text(text).map_err(describe_enigo_error)?;
+            Ok(format!("Typed: {}", text))
+        }
+        Action::Wait(ms) => {
+            automation.sleep(token, std::time::Duration::from_millis(*ms))?;
+            Ok(format!("Waited {}ms", ms))
+        }
+        Action::Shell(command) => {
+            let output = automation.output(&mut shell_command(config, command)).map_err(|e| e.to_string())?;
+            if output.status.success() {
+                Ok(String::from_utf8_lossy(&output.stdout).to_string())
+            } else {
+                Err(String::from_utf8_lossy(&output.stderr).to_string())
+            }
+        }
+    }
+}
+
+// Macro file commands
+// One recorded input event. Keys and buttons use the same names as the
+// keyboard and mouse commands; Wait is a pause in milliseconds.
+#[derive(serde::Serialize, serde::Deserialize)]
+enum InputEvent {
+    MouseMove { x: i32, y: i32 },
+    MouseDown(String),
+    MouseUp(String),
+    Scroll(i32),
+    KeyDown(String),
+    KeyUp(String),
+    Type(String),
+    Wait(u64),
+}
+
+// Bump when the event format changes; older files must keep loading
+const MACRO_FORMAT_VERSION: u32 = 1;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MacroFile {
+    version: u32,
+    events: Vec<InputEvent>,
+}
+
+#[tauri::command]
+fn save_macro(events: Vec<InputEvent>, path: String) -> Result<String, String> {
+    let count = events.len();
+    let file = MacroFile { version: MACRO_FORMAT_VERSION, events };
+    let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())?;
+    Ok(format!("Saved {} events to {}", count, path))
+}
+
+#[tauri::command]
+fn load_macro(path: String) -> Result<Vec<InputEvent>, String> {
+    let json = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let file: MacroFile = serde_json::from_str(&json).map_err(|e| format!("Invalid macro file {}: {}", path, e))?;
+    if file.version > MACRO_FORMAT_VERSION {
+        return Err(format!("Macro file {} uses format version {}, this app supports up to {}", path, file.version, MACRO_FORMAT_VERSION));
+    }
+    Ok(file.events)
+}
+
+// File system commands
+fn digest_file<D: sha2::Digest>(path: &str) -> Result<String, String> {
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut hasher = D::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    // Read in chunks so large files are never held in memory at once
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[tauri::command]
+fn hash_file(path: String, algorithm: String) -> Result<String, String> {
+    match algorithm.to_lowercase().as_str() {
+        "md5" => digest_file::<md5::Md5>(&path),
+        "sha1" => digest_file::<sha1::Sha1>(&path),
+        "sha256" => digest_file::<sha2::Sha256>(&path),
+        _ => Err(format!("Unsupported hash algorithm: {}. Use 'md5', 'sha1', or 'sha256'", algorithm)),
+    }
+}
+
+#[tauri::command]
+fn path_exists(path: String) -> bool {
+    std::path::Path::new(&path).exists()
+}
+
+#[derive(serde::Serialize)]
+struct DirEntryInfo {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    // Milliseconds since the Unix epoch
+    modified: Option<u64>,
+}
+
+fn unix_millis(time: std::time::SystemTime) -> Option<u64> {
+    time.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_millis() as u64)
+}
+
+#[tauri::command]
+fn list_dir(path: String) -> Result<Vec<DirEntryInfo>, String> {
+    let dir = std::path::Path::new(&path);
+    if !dir.exists() {
+        return Err(format!("Path does not exist: {}", path));
+    }
+    if !dir.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let metadata = entry.metadata().map_err(|e| e.to_string())?;
+        entries.push(DirEntryInfo {
+            name: entry.file_name().to_string_lossy().to_string(),
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: metadata.modified().ok().and_then(unix_millis),
+        });
+    }
+
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+#[derive(serde::Serialize)]
+struct FileInfo {
+    size: u64,
+    // Milliseconds since the Unix epoch; created is missing on filesystems that don't track it
+    created: Option<u64>,
+    modified: Option<u64>,
+    is_dir: bool,
+    readonly: bool,
+    // None for directories
+    mime_type: Option<String>,
+}
+
+// Magic bytes first, since downloads often have wrong or missing extensions
+const MIME_SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"Rar!\x1a\x07", "application/vnd.rar"),
+    (b"ID3", "audio/mpeg"),
+    (b"OggS", "audio/ogg"),
+    (b"fLaC", "audio/flac"),
+    (b"\x7fELF", "application/x-executable"),
+    (b"MZ", "application/vnd.microsoft.portable-executable"),
+];
+
+const MIME_EXTENSIONS: &[(&str, &str)] = &[
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "text/javascript"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+    ("bmp", "image/bmp"),
+    ("ico", "image/x-icon"),
+    ("wav", "audio/wav"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+    ("mov", "video/quicktime"),
+    ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+    ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+    ("tar", "application/x-tar"),
+];
+
+fn guess_mime_type(path: &std::path::Path) -> String {
+    use std::io::Read;
+
+    let extension = path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let by_extension = MIME_EXTENSIONS.iter().find(|(ext, _)| *ext == extension).map(|(_, mime)| *mime);
+
+    let mut header = [0u8; 512];
+    let read = std::fs::File::open(path).and_then(|mut file| file.read(&mut header)).unwrap_or(0);
+    let by_content = MIME_SIGNATURES.iter()
+        .find(|(magic, _)| header[..read].starts_with(magic))
+        .map(|(_, mime)| *mime);
+
+    let mime = match (by_content, by_extension) {
+        // Office documents are zip files; the extension is more specific
+        (Some("application/zip"), Some(specific)) => specific,
+        (Some(mime), _) => mime,
+        (None, Some(mime)) => mime,
+        // A multi-byte character cut off at the end of the sample still counts as text
+        (None, None) if read > 0 && std::str::from_utf8(&header[..read]).map_or_else(|e| e.error_len().is_none(), |_| true) => "text/plain",
+        (None, None) => "application/octet-stream",
+    };
+    mime.to_string()
+}
+
+#[tauri::command]
+fn file_info(path: String) -> Result<FileInfo, String> {
+    let file = std::path::Path::new(&path);
+    let metadata = std::fs::metadata(file).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!("Path does not exist: {}", path),
+        _ => format!("Failed to read metadata for {}: {}", path, e),
+    })?;
+
+    Ok(FileInfo {
+        size: metadata.len(),
+        created: metadata.created().ok().and_then(unix_millis),
+        modified: metadata.modified().ok().and_then(unix_millis),
+        is_dir: metadata.is_dir(),
+        readonly: metadata.permissions().readonly(),
+        mime_type: (!metadata.is_dir()).then(|| guess_mime_type(file)),
+    })
+}
+
+// Archive commands
+// zip and tar.gz are handled in-process, so neither unzip nor tar has to be
+// installed. "archive-progress" reports entries done (total is known for zip
+// only) at most every 200ms, plus once at the end.
+#[derive(Clone, serde::Serialize)]
+struct ArchiveProgress {
+    archive: String,
+    done: u64,
+    total: Option<u64>,
+}
+
+struct ProgressReporter {
+    app: tauri::AppHandle,
+    archive: String,
+    total: Option<u64>,
+    done: u64,
+    last_emit: std::time::Instant,
+}
+
+impl ProgressReporter {
+    fn new(app: tauri::AppHandle, archive: &str, total: Option<u64>) -> Self {
+        ProgressReporter { app, archive: archive.to_string(), total, done: 0, last_emit: std::time::Instant::now() }
+    }
+
+    fn advance(&mut self) {
+        self.done += 1;
+        if self.last_emit.elapsed() >= std::time::Duration::from_millis(200) {
+            self.emit();
+        }
+    }
+
+    fn emit(&mut self) {
+        self.last_emit = std::time::Instant::now();
+        self.app.emit("archive-progress", ArchiveProgress {
+            archive: self.archive.clone(),
+            done: self.done,
+            total: self.total,
+        }).ok();
+    }
+}
+
+fn is_tar_gz(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    lower.ends_with(".tar.gz") || lower.ends_with(".tgz")
+}
+
+#[tauri::command(async)]
+fn extract_archive(app: tauri::AppHandle, path: String, dest: String) -> Result<String, String> {
+    let file = std::fs::File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let dest_dir = std::path::Path::new(&dest);
+    std::fs::create_dir_all(dest_dir).map_err(|e| format!("Failed to create {}: {}", dest, e))?;
+
+    let mut reporter = if is_tar_gz(&path) {
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(std::io::BufReader::new(file)));
+        let mut reporter = ProgressReporter::new(app, &path, None);
+        for entry in archive.entries().map_err(|e| format!("Invalid archive {}: {}", path, e))? {
+            let mut entry = entry.map_err(|e| format!("Invalid archive {}: {}", path, e))?;
+            // unpack_in refuses entries that would land outside dest
+            entry.unpack_in(dest_dir).map_err(|e| format!("Failed to extract from {}: {}", path, e))?;
+            reporter.advance();
+        }
+        reporter
+    } else {
+        let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Invalid archive {}: {}", path, e))?;
+        let mut reporter = ProgressReporter::new(app, &path, Some(archive.len() as u64));
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index).map_err(|e| format!("Invalid archive {}: {}", path, e))?;
+            // Names with .. or an absolute path would land outside dest
+            let name = entry.enclosed_name().ok_or(format!("Unsafe path in archive: {}", entry.name()))?;
+            let target = dest_dir.join(name);
+            if entry.is_dir() {
+                std::fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+            } else {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                let mut out = std::fs::File::create(&target)
+                    .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+                std::io::copy(&mut entry, &mut out).map_err(|e| format!("Failed to extract {}: {}", entry.name(), e))?;
+                #[cfg(unix)]
+                if let Some(mode) = entry.unix_mode() {
+                    use std::os::unix::fs::PermissionsExt;
+                    std::fs::set_permissions(&target, std::fs::Permissions::from_mode(mode)).ok();
+                }
+            }
+            reporter.advance();
+        }
+        reporter
+    };
+
+    reporter.emit();
+    Ok(format!("Extracted {} entries from {} to {}", reporter.done, path, dest))
+}
+
+// format: "zip" or "tar.gz". Directories are added with everything under
+// them, each path under its own name at the archive root. Symlinks are
+// skipped rather than followed, so a link back up the tree can't loop.
+#[tauri::command(async)]
+fn create_archive(app: tauri::AppHandle, paths: Vec<String>, dest: String, format: String) -> Result<String, String> {
+    let format = format.to_lowercase();
+    if format != "zip" && format != "tar.gz" {
+        return Err(format!("Unsupported archive format: {}. Use 'zip' or 'tar.gz'", format));
+    }
+
+    // (file on disk, name inside the archive); directories end up in order before their contents
+    let mut entries: Vec<(std::path::PathBuf, String)> = Vec::new();
+    for path in &paths {
+        let source = std::path::Path::new(path);
+        if !source.exists() {
+            return Err(format!("Path does not exist: {}", path));
+        }
+        let name = source.file_name().map(|name| name.to_string_lossy().to_string())
+            .ok_or(format!("Can't archive {} (no file name)", path))?;
+        collect_archive_entries(source, name, &mut entries).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    }
+
+    let file = std::fs::File::create(&dest).map_err(|e| format!("Failed to create {}: {}", dest, e))?;
+    let mut reporter = ProgressReporter::new(app, &dest, Some(entries.len() as u64));
+    if format == "zip" {
+        let mut writer = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .large_file(true);
+        for (source, name) in &entries {
+            if source.is_dir() {
+                writer.add_directory(name.as_str(), options).map_err(|e| e.to_string())?;
+            } else {
+                writer.start_file(name.as_str(), options).map_err(|e| e.to_string())?;
+                let mut input = std::fs::File::open(source).map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+                std::io::copy(&mut input, &mut writer).map_err(|e| format!("Failed to add {}: {}", source.display(), e))?;
+            }
+            reporter.advance();
+        }
+        writer.finish().map_err(|e| e.to_string())?;
+    } else {
+        let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (source, name) in &entries {
+            builder.append_path_with_name(source, name).map_err(|e| format!("Failed to add {}: {}", source.display(), e))?;
+            reporter.advance();
+        }
+        builder.into_inner().and_then(|encoder| encoder.finish()).map_err(|e| e.to_string())?;
+    }
+
+    reporter.emit();
+    Ok(format!("Created {} with {} entries", dest, entries.len()))
+}
+
+// Archive names always use /, whatever the platform separator
+fn collect_archive_entries(
+    path: &std::path::Path,
+    name: String,
+    entries: &mut Vec<(std::path::PathBuf, String)>,
+) -> std::io::Result<()> {
+    let file_type = std::fs::symlink_metadata(path)?.file_type();
+    if file_type.is_symlink() {
+        return Ok(());
+    }
+    if file_type.is_dir() {
+        let mut children: Vec<_> = std::fs::read_dir(path)?.collect::<Result<_, _>>()?;
+        children.sort_by_key(|child| child.file_name());
+        entries.push((path.to_path_buf(), format!("{}/", name)));
+        for child in children {
+            let child_name = format!("{}/{}", name, child.file_name().to_string_lossy());
+            collect_archive_entries(&child.path(), child_name, entries)?;
+        }
+    } else {
+        entries.push((path.to_path_buf(), name));
+    }
+    Ok(())
+}
+
+// Runs on the thread pool so polling doesn't block the main thread
+#[tauri::command(async)]
+fn wait_for_file(path: String, timeout_ms: u64, automation: tauri::State<'_, Automation>) -> bool {
+    let token = automation.token();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_millis(timeout_ms);
+    let path = std::path::Path::new(&path);
+
+    loop {
+        if path.exists() {
+            return true;
+        }
+        if std::time::Instant::now() >= deadline {
+            return false;
+        }
+        if automation.sleep(token, std::time::Duration::from_millis(250)).is_err() {
+            return false;
+        }
+    }
+}
+
+// System state commands
+#[tauri::command]
+fn is_screen_locked() -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::System::StationsAndDesktops::{CloseDesktop, OpenInputDesktop, SwitchDesktop, DESKTOP_SWITCHDESKTOP};
+
+        // While locked the input desktop is the secure Winlogon desktop,
+        // which a normal process can neither open nor switch to
+        unsafe {
+            let desktop = OpenInputDesktop(0, 0, DESKTOP_SWITCHDESKTOP);
+            if desktop.is_null() {
+                return true;
+            }
+            let switched = SwitchDesktop(desktop) != 0;
+            CloseDesktop(desktop);
+            !switched
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // ioreg exposes the CGSession dictionary of the console session
+        Command::new("ioreg")
+            .args(["-n", "Root", "-d1"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains("\"CGSSessionScreenIsLocked\"=Yes"))
+            .unwrap_or(false)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "self".to_string());
+        Command::new("loginctl")
+            .args(["show-session", &session, "-p", "LockedHint"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "LockedHint=yes")
+            .unwrap_or(false)
+    }
+}
+
+// Polls total CPU usage (percent, 0-100) until it falls below cpu_threshold.
+// Returns false if the machine is still busy when timeout_ms runs out.
+#[tauri::command(async)]
+fn wait_for_system_idle(cpu_threshold: f32, timeout_ms: u64, automation: tauri::State<'_, Automation>) -> bool {
+    let token = automation.token();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_millis(timeout_ms);
+    let mut system = sysinfo::System::new();
+    // Usage is measured between two refreshes, so prime the first sample
+    system.refresh_cpu_usage();
+
+    loop {
+        if automation.sleep(token, std::time::Duration::from_millis(500).max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL)).is_err() {
+            return false;
+        }
+        system.refresh_cpu_usage();
+        if system.global_cpu_usage() < cpu_threshold {
+            return true;
+        }
+        if std::time::Instant::now() >= deadline {
+            return false;
+        }
+    }
+}
+
+// priority: "low", "normal" or "high". Raising priority usually needs admin
+// (Windows "high" is allowed, but Unix negative nice values need root).
+#[tauri::command]
+fn set_process_priority(pid: u32, priority: String) -> Result<String, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Threading::{
+            OpenProcess, SetPriorityClass, BELOW_NORMAL_PRIORITY_CLASS, HIGH_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS,
+            PROCESS_SET_INFORMATION,
+        };
+
+        let class = match priority.as_str() {
+            "low" => BELOW_NORMAL_PRIORITY_CLASS,
+            "normal" => NORMAL_PRIORITY_CLASS,
+            "high" => HIGH_PRIORITY_CLASS,
+            _ => return Err(format!("Invalid priority: {}. Use 'low', 'normal', or 'high'", priority)),
+        };
+        unsafe {
+            let process = OpenProcess(PROCESS_SET_INFORMATION, 0, pid);
+            if process.is_null() {
+                return Err(format!("Cannot open process {}: {}", pid, std::io::Error::last_os_error()));
+            }
+            let applied = SetPriorityClass(process, class) != 0;
+            let error = std::io::Error::last_os_error();
+            CloseHandle(process);
+            if !applied {
+                return Err(format!("Failed to set priority of process {}: {}", pid, error));
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let nice: libc::c_int = match priority.as_str() {
+            "low" => 10,
+            "normal" => 0,
+            "high" => -10,
+            _ => return Err(format!("Invalid priority: {}. Use 'low', 'normal', or 'high'", priority)),
+        };
+        // Sets the absolute nice value; renice -n is an increment on macOS and BSD
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS as _, pid as libc::id_t, nice) } != 0 {
+            return Err(format!("Failed to set priority of process {}: {}", pid, std::io::Error::last_os_error()));
+        }
+    }
+
+    Ok(format!("Set priority of process {} to {}", pid, priority))
+}
+
+#[derive(serde::Serialize)]
+struct NetworkInterface {
+    name: String,
+    ip: String,
+}
+
+#[derive(serde::Serialize)]
+struct NetworkStatus {
+    online: bool,
+    // One entry per address; loopback interfaces are left out
+    interfaces: Vec<NetworkInterface>,
+}
+
+// Without `probe`, online only means some interface has a non-loopback
+// address. With it, a TCP connection to public DNS servers (2s timeout)
+// confirms the internet is actually reachable.
+#[tauri::command(async)]
+fn get_network_status(probe: Option<bool>) -> NetworkStatus {
+    let networks = sysinfo::Networks::new_with_refreshed_list();
+    let mut interfaces: Vec<NetworkInterface> = networks
+        .iter()
+        .flat_map(|(name, data)| {
+            data.ip_networks()
+                .iter()
+                .filter(|network| !network.addr.is_loopback())
+                .map(move |network| NetworkInterface { name: name.clone(), ip: network.addr.to_string() })
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name).then(a.ip.cmp(&b.ip)));
+
+    let online = if probe.unwrap_or(false) {
+        ["1.1.1.1:53", "8.8.8.8:53"].iter().any(|address| {
+            address.parse().is_ok_and(|address| {
+                std::net::TcpStream::connect_timeout(&address, std::time::Duration::from_secs(2)).is_ok()
+            })
+        })
+    } else {
+        !interfaces.is_empty()
+    };
+
+    NetworkStatus { online, interfaces }
+}
+
+// The main window follows the OS appearance unless set_theme overrides it.
+// "theme-changed" (payload: true when dark) is emitted whenever the theme it
+// is drawn with flips.
+#[derive(Default)]
+struct ThemeOverride {
+    theme: Mutex<Option<tauri::Theme>>,
+}
+
+// Whether the OS appearance is dark, regardless of any override
+#[tauri::command]
+fn is_dark_mode(app: tauri::AppHandle, theme_override: tauri::State<'_, ThemeOverride>) -> bool {
+    // Without an override the window's theme is the system's
+    if theme_override.theme.lock().unwrap().is_none() {
+        return window_theme_is_dark(&app);
+    }
+    system_prefers_dark().unwrap_or(false)
+}
+
+fn window_theme_is_dark(app: &tauri::AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|window| window.theme().ok())
+        .map(|theme| theme == tauri::Theme::Dark)
+        .unwrap_or(false)
+}
+
+// Reads the OS appearance setting directly, for when windows are overridden
+fn system_prefers_dark() -> Option<bool> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::System::Registry::RRF_RT_REG_DWORD;
+
+        let data = read_user_registry(r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize", "AppsUseLightTheme", RRF_RT_REG_DWORD)?;
+        Some(u32::from_le_bytes(data.get(..4)?.try_into().ok()?) == 0)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // The key only exists while dark mode is on
+        let output = Command::new("defaults").args(["read", "-g", "AppleInterfaceStyle"]).output().ok()?;
+        Some(output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "Dark")
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let output = Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+            .output()
+            .ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).contains("dark"))
+    }
+}
+
+// theme: "light", "dark", or "system" to go back to following the OS
+#[tauri::command]
+fn set_theme(app: tauri::AppHandle, theme: String, theme_override: tauri::State<'_, ThemeOverride>) -> Result<String, String> {
+    let override_theme = match theme.to_lowercase().as_str() {
+        "light" => Some(tauri::Theme::Light),
+        "dark" => Some(tauri::Theme::Dark),
+        "system" => None,
+        _ => return Err(format!("Unknown theme: {} (expected light, dark, or system)", theme)),
+    };
+    *theme_override.theme.lock().unwrap() = override_theme;
+    app.set_theme(override_theme);
+    Ok(format!("Theme set to {}", theme.to_lowercase()))
+}
+
+#[derive(serde::Serialize)]
+struct ThemeInfo {
+    // What windows are drawn with: "light" or "dark"
+    theme: String,
+    // What set_theme chose: "light", "dark", or "system"
+    mode: String,
+}
+
+#[tauri::command]
+fn get_theme(app: tauri::AppHandle, theme_override: tauri::State<'_, ThemeOverride>) -> ThemeInfo {
+    let mode = match *theme_override.theme.lock().unwrap() {
+        Some(tauri::Theme::Dark) => "dark",
+        Some(_) => "light",
+        None => "system",
+    };
+    ThemeInfo {
+        theme: if window_theme_is_dark(&app) { "dark" } else { "light" }.to_string(),
+        mode: mode.to_string(),
+    }
+}
+
+// OS settings commands
+// domain names where the setting lives:
+//   Windows: a registry key with its hive, e.g. HKCU\Control Panel\Desktop
+//            (key is the value name, "" for the default value)
+//   macOS:   a defaults domain, e.g. com.apple.dock or NSGlobalDomain
+//   Linux:   an ini-style file, absolute or relative to ~/.config, e.g.
+//            gtk-3.0/settings.ini (key is "name" or "section/name")
+#[cfg(windows)]
+fn parse_registry_domain(domain: &str) -> Result<(windows_sys::Win32::System::Registry::HKEY, &str), String> {
+    use windows_sys::Win32::System::Registry::{HKEY_CLASSES_ROOT, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, HKEY_USERS};
+
+    let (hive, subkey) = domain.split_once('\\').unwrap_or((domain, ""));
+    let hive = match hive.to_uppercase().as_str() {
+        "HKCU" | "HKEY_CURRENT_USER" => HKEY_CURRENT_USER,
+        "HKLM" | "HKEY_LOCAL_MACHINE" => HKEY_LOCAL_MACHINE,
+        "HKCR" | "HKEY_CLASSES_ROOT" => HKEY_CLASSES_ROOT,
+        "HKU" | "HKEY_USERS" => HKEY_USERS,
+        _ => return Err(format!("Unknown registry hive: {}. Use HKCU, HKLM, HKCR, or HKU", hive)),
+    };
+    Ok((hive, subkey))
+}
+
+#[cfg(target_os = "linux")]
+fn setting_file(domain: &str) -> std::path::PathBuf {
+    let path = std::path::Path::new(domain);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let config_home = std::env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| format!("{}/.config", std::env::var("HOME").unwrap_or_default()));
+    std::path::Path::new(&config_home).join(path)
+}
+
+// The line index of `name` inside `section` ("" for keys before any header)
+#[cfg(target_os = "linux")]
+fn find_ini_entry(lines: &[&str], key: &str) -> Option<usize> {
+    let (section, name) = key.rsplit_once('/').unwrap_or(("", key));
+    let mut current = "";
+    lines.iter().position(|line| {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            current = header.trim();
+            return false;
+        }
+        current == section && line.split_once('=').is_some_and(|(entry, _)| entry.trim() == name)
+    })
+}
+
+// Most registry values come back as text; binary data is shown as hex
+#[tauri::command]
+fn read_setting(domain: String, key: String) -> Result<Option<String>, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::System::Registry::{RRF_RT_ANY, REG_DWORD, REG_EXPAND_SZ, REG_MULTI_SZ, REG_QWORD, REG_SZ};
+
+        let (hive, subkey) = parse_registry_domain(&domain)?;
+        let Some((kind, data)) = read_registry(hive, subkey, &key, RRF_RT_ANY) else {
+            return Ok(None);
+        };
+        let text = |data: &[u8]| {
+            let wide: Vec<u16> = data.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+            String::from_utf16_lossy(&wide)
+        };
+        let value = match kind {
+            REG_SZ | REG_EXPAND_SZ => text(&data).trim_end_matches('\0').to_string(),
+            REG_MULTI_SZ => text(&data).split('\0').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("\n"),
+            REG_DWORD if data.len() >= 4 => u32::from_le_bytes([data[0], data[1], data[2], data[3]]).to_string(),
+            REG_QWORD if data.len() >= 8 => u64::from_le_bytes(data[..8].try_into().unwrap()).to_string(),
+            _ => data.iter().map(|b| format!("{:02x}", b)).collect(),
+        };
+        Ok(Some(value))
+    }
+
+    // defaults exits non-zero when the domain or key doesn't exist
+    #[cfg(target_os = "macos")]
+    {
+        let output = Command::new("defaults")
+            .args(["read", &domain, &key])
+            .output()
+            .map_err(|e| format!("Failed to run defaults: {}", e))?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(&output.stdout).trim_end().to_string()))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let contents = match std::fs::read_to_string(setting_file(&domain)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read {}: {}", domain, e)),
+        };
+        let lines: Vec<&str> = contents.lines().collect();
+        Ok(find_ini_entry(&lines, &key)
+            .and_then(|index| lines[index].split_once('='))
+            .map(|(_, value)| value.trim().to_string()))
+    }
+}
+
+// Writing OS settings can break things, so write_setting needs a token from
+// request_setting_write_consent, whose native dialog shows the user the exact
+// domain, key and value. The token is good for that one write only.
+fn setting_write_action(domain: &str, key: &str, value: &str) -> String {
+    serde_json::json!(["write-setting", domain, key, value]).to_string()
+}
+
+#[tauri::command(async)]
+fn request_setting_write_consent(
+    app: tauri::AppHandle,
+    domain: String,
+    key: String,
+    value: String,
+    consent: tauri::State<'_, ConsentTokens>,
+) -> Result<String, String> {
+    consent.request(
+        &app,
+        "Allow changing a system setting?",
+        &format!("PAL wants to change a system setting.\n\nDomain: {}\nKey: {}\nNew value: {}", domain, key, value),
+        setting_write_action(&domain, &key, &value),
+    )
+}
+
+// Writes keep the type of an existing registry value (DWORD and QWORD values
+// need a number); new values are created as strings
+#[tauri::command]
+fn write_setting(domain: String, key: String, value: String, consent_token: String, consent: tauri::State<'_, ConsentTokens>) -> Result<String, String> {
+    if !consent.consume(&consent_token, &setting_write_action(&domain, &key, &value)) {
+        return Err("Writing OS settings needs the user's explicit approval; call request_setting_write_consent first".to_string());
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::System::Registry::{
+            RegSetKeyValueW, REG_DWORD, REG_EXPAND_SZ, REG_QWORD, REG_SZ, RRF_NOEXPAND, RRF_RT_ANY,
+        };
+
+        let (hive, subkey) = parse_registry_domain(&domain)?;
+        let existing = read_registry(hive, subkey, &key, RRF_RT_ANY | RRF_NOEXPAND).map(|(kind, _)| kind);
+        let number_error = |e: std::num::ParseIntError| format!("{}\\{} holds a number, got '{}': {}", domain, key, value, e);
+        let string = || wide_null(&value).iter().flat_map(|unit| unit.to_le_bytes()).collect::<Vec<u8>>();
+        let (kind, data) = match existing {
+            Some(REG_DWORD) => (REG_DWORD, value.trim().parse::<u32>().map_err(number_error)?.to_le_bytes().to_vec()),
+            Some(REG_QWORD) => (REG_QWORD, value.trim().parse::<u64>().map_err(number_error)?.to_le_bytes().to_vec()),
+            Some(REG_EXPAND_SZ) => (REG_EXPAND_SZ, string()),
+            Some(REG_SZ) | None => (REG_SZ, string()),
+            Some(_) => return Err(format!("{}\\{} is not a string or number value, so it can't be written", domain, key)),
+        };
+        let (subkey, name) = (wide_null(subkey), wide_null(&key));
+        let status = unsafe {
+            RegSetKeyValueW(hive, subkey.as_ptr(), name.as_ptr(), kind, data.as_ptr() as *const _, data.len() as u32)
+        };
+        if status != 0 {
+            return Err(format!("Failed to write {}\\{}: {}", domain, key, std::io::Error::from_raw_os_error(status as i32)));
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let output = Command::new("defaults")
+            .args(["write", &domain, &key, &value])
+            .output()
+            .map_err(|e| format!("Failed to run defaults: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+    }
+
+    // Replaces the entry in place, or appends it (and its section) if missing
+    #[cfg(target_os = "linux")]
+    {
+        let path = setting_file(&domain);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", domain, e)),
+        };
+        let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+        let (section, name) = key.rsplit_once('/').unwrap_or(("", key.as_str()));
+        let entry = format!("{}={}", name, value);
+        let borrowed: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let header = format!("[{}]", section);
+        match find_ini_entry(&borrowed, &key) {
+            Some(index) => lines[index] = entry,
+            None if section.is_empty() => lines.insert(0, entry),
+            None => match lines.iter().position(|line| line.trim() == header) {
+                Some(index) => lines.insert(index + 1, entry),
+                None => lines.extend([header, entry]),
+            },
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&path, lines.join("\n") + "\n").map_err(|e| format!("Failed to write {}: {}", domain, e))?;
+    }
+
+    Ok(format!("Set {} {} to {}", domain, key, value))
+}
+
+// Audio feedback commands
+// sound: "beep", "success", "error", or the path of a wav/mp3/ogg/flac file.
+// The built-in cues are generated tones, so no assets need to ship.
+#[tauri::command(async)]
+fn play_sound(sound: String) -> Result<String, String> {
+    use rodio::Source;
+
+    let (_stream, handle) = rodio::OutputStream::try_default().map_err(|e| e.to_string())?;
+    let sink = rodio::Sink::try_new(&handle).map_err(|e| e.to_string())?;
+    let tone = |hz: f32, ms: u64| rodio::source::SineWave::new(hz)
+        .take_duration(std::time::Duration::from_millis(ms))
+        .amplify(0.2);
+
+    match sound.to_lowercase().as_str() {
+        "beep" => sink.append(tone(880.0, 200)),
+        "success" => {
+            sink.append(tone(660.0, 120));
+            sink.append(tone(880.0, 180));
+        }
+        "error" => {
+            sink.append(tone(330.0, 150));
+            sink.append(tone(220.0, 250));
+        }
+        _ => {
+            let file = std::fs::File::open(&sound).map_err(|e| format!("Failed to open sound {}: {}", sound, e))?;
+            let source = rodio::Decoder::new(std::io::BufReader::new(file))
+                .map_err(|e| format!("Failed to load sound {}: {}", sound, e))?;
+            sink.append(source);
+        }
+    }
+
+    // The stream stops when dropped, so wait for playback to finish
+    sink.sleep_until_end();
+    Ok(format!("Played sound: {}", sound))
+}
+
+// Input hook commands
+// rdev's global listener can't be stopped and only one may run per process,
+// so a single hook thread starts on first use and serves both key capture
+// and latency probes, ignoring events while neither wants them.
+#[derive(Default)]
+struct InputHook {
+    state: Arc<Mutex<InputHookState>>,
+}
+
+#[derive(Default)]
+struct InputHookState {
+    listening: bool,
+    capturing: bool,
+    buffer: String,
+    // Label of the on-screen recording indicator while capturing
+    indicator: Option<String>,
+    // Receives the arrival time of the next mouse move
+    latency_probe: Option<std::sync::mpsc::Sender<std::time::Instant>>,
+}
+
+// Starts the hook thread if needed; true if it was only just started
+fn ensure_input_hook(app: &tauri::AppHandle, hook: &InputHook, state: &mut InputHookState) -> bool {
+    if state.listening {
+        return false;
+    }
+
+    let shared = hook.state.clone();
+    let handle = app.clone();
+    std::thread::spawn(move || {
+        let hook_state = shared.clone();
+        let result = rdev::listen(move |event| {
+            let received = std::time::Instant::now();
+            let mut state = hook_state.lock().unwrap();
+            match event.event_type {
+                rdev::EventType::MouseMove { .. } => {
+                    if let Some(probe) = state.latency_probe.take() {
+                        probe.send(received).ok();
+                    }
+                }
+                rdev::EventType::KeyPress(key) if state.capturing => {
+                    match key {
+                        rdev::Key::Backspace => {
+                            state.buffer.pop();
+                        }
+                        rdev::Key::Return | rdev::Key::KpReturn => state.buffer.push('\n'),
+                        _ => {
+                            if let Some(text) = event.name.filter(|text| !text.chars().any(char::is_control)) {
+                                state.buffer.push_str(&text);
+                            }
+                        }
+                    }
+
+                    let excess = state.buffer.chars().count().saturating_sub(KEY_CAPTURE_LIMIT);
+                    if excess > 0 {
+                        let cut = state.buffer.char_indices().nth(excess).map(|(i, _)| i).unwrap_or(state.buffer.len());
+                        state.buffer.drain(..cut);
+                    }
+                }
+                _ => {}
+            }
+        });
+
+        // Only reached if the hook couldn't be installed (e.g. no permission)
+        if result.is_err() {
+            let mut state = shared.lock().unwrap();
+            state.listening = false;
+            state.latency_probe = None;
+            state.buffer.clear();
+            let indicator = state.indicator.take();
+            if std::mem::take(&mut state.capturing) {
+                handle.emit("key-capture-active", false).ok();
+            }
+            drop(state);
+            close_window(&handle, indicator);
+        }
+    });
+    state.listening = true;
+    true
+}
+
+// User consent for sensitive commands
+// Tokens are issued only after the user approves a native dialog, which page
+// scripts can neither draw nor click. Each token approves one exact action,
+// once, and expires after CONSENT_TOKEN_TTL.
+const CONSENT_TOKEN_TTL: std::time::Duration = std::time::Duration::from_secs(120);
+
+#[derive(Default)]
+struct ConsentTokens {
+    // Token -> the action it approves and when it was issued
+    issued: Mutex<std::collections::HashMap<String, (String, std::time::Instant)>>,
+}
+
+impl ConsentTokens {
+    // Asks the user and returns a fresh token for `action` if they allow it.
+    // Blocks until the dialog closes, so callers must not be on the main thread.
+    fn request(&self, app: &tauri::AppHandle, title: &str, message: &str, action: String) -> Result<String, String> {
+        use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+        let approved = app.dialog()
+            .message(message)
+            .title(title)
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom("Allow".to_string(), "Don't Allow".to_string()))
+            .blocking_show();
+        if !approved {
+            return Err("The user declined".to_string());
+        }
+
+        let mut bytes = [0u8; 16];
+        getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+        let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let mut issued = self.issued.lock().unwrap();
+        issued.retain(|_, (_, issued_at)| issued_at.elapsed() < CONSENT_TOKEN_TTL);
+        issued.insert(token.clone(), (action, std::time::Instant::now()));
+        Ok(token)
+    }
+
+    // Spends the token; true only if it was issued for exactly `action` and
+    // hasn't expired
+    fn consume(&self, token: &str, action: &str) -> bool {
+        match self.issued.lock().unwrap().remove(token) {
+            Some((approved, issued_at)) => approved == action && issued_at.elapsed() < CONSENT_TOKEN_TTL,
+            None => false,
+        }
+    }
+}
+
+fn close_window(app: &tauri::AppHandle, label: Option<String>) {
+    if let Some(window) = label.and_then(|label| app.get_webview_window(&label)) {
+        window.close().ok();
+    }
+}
+
+// Opt-in recording of typed text, so the assistant can offer to fix what the
+// user just wrote. start_key_capture needs a token from
+// request_key_capture_consent, which asks the user in a native dialog. While
+// recording, a click-through badge created here stays on top of everything;
+// closing it by any means ends the capture. "key-capture-active" (bool) is
+// emitted on every start and stop too. The text stays in memory and is
+// cleared once stop_key_capture hands it back.
+const KEY_CAPTURE_ACTION: &str = "key-capture";
+// Only the most recent characters are kept
+const KEY_CAPTURE_LIMIT: usize = 10_000;
+
+#[tauri::command(async)]
+fn request_key_capture_consent(app: tauri::AppHandle, consent: tauri::State<'_, ConsentTokens>) -> Result<String, String> {
+    consent.request(
+        &app,
+        "Allow keystroke recording?",
+        "PAL will record everything you type, in every app, until recording is stopped. A \"Recording keystrokes\" badge stays on screen the whole time.",
+        KEY_CAPTURE_ACTION.to_string(),
+    )
+}
+
+// Shows the recording badge in the top-right corner of the primary display
+fn show_key_capture_indicator(app: &tauri::AppHandle, hook: &InputHook) -> Result<String, String> {
+    static NEXT_ID: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+    const WIDTH: f64 = 200.0;
+    const HEIGHT: f64 = 36.0;
+    const MARGIN: f64 = 12.0;
+
+    // Unique label, so a late Destroyed event from an earlier badge can't end a new capture
+    let label = format!("key-capture-indicator-{}", NEXT_ID.fetch_add(1, Ordering::SeqCst));
+    let indicator = tauri::WebviewWindowBuilder::new(app, &label, tauri::WebviewUrl::App("key-capture-indicator.html".into()))
+        .decorations(false)
+        .transparent(true)
+        .always_on_top(true)
+        .visible_on_all_workspaces(true)
+        .skip_taskbar(true)
+        .resizable(false)
+        .focused(false)
+        .shadow(false)
+        .inner_size(WIDTH, HEIGHT)
+        .visible(false)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let shared = hook.state.clone();
+    let handle = app.clone();
+    let closed = label.clone();
+    indicator.on_window_event(move |event| {
+        if let tauri::WindowEvent::Destroyed = event {
+            let mut state = shared.lock().unwrap();
+            if state.indicator.as_deref() == Some(closed.as_str()) {
+                // Closed by something other than stop_key_capture
+                state.indicator = None;
+                state.capturing = false;
+                state.buffer.clear();
+                handle.emit("key-capture-active", false).ok();
+            }
+        }
+    });
+
+    let placed = app.primary_monitor()
+        .map_err(|e| e.to_string())
+        .and_then(|monitor| monitor.ok_or("No primary display".to_string()))
+        .and_then(|monitor| {
+            let scale = monitor.scale_factor();
+            let x = monitor.position().x + monitor.size().width as i32 - ((WIDTH + MARGIN) * scale) as i32;
+            let y = monitor.position().y + (MARGIN * scale) as i32;
+            indicator.set_ignore_cursor_events(true)
+                .and_then(|_| indicator.set_position(tauri::PhysicalPosition::new(x, y)))
+                .and_then(|_| indicator.show())
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = placed {
+        indicator.close().ok();
+        return Err(format!("Failed to show the recording indicator: {}", e));
+    }
+    Ok(label)
+}
+
+#[tauri::command(async)]
+fn start_key_capture(
+    app: tauri::AppHandle,
+    consent_token: String,
+    hook: tauri::State<'_, InputHook>,
+    consent: tauri::State<'_, ConsentTokens>,
+) -> Result<String, String> {
+    if hook.state.lock().unwrap().capturing {
+        return Ok("Key capture already running".to_string());
+    }
+    if !consent.consume(&consent_token, KEY_CAPTURE_ACTION) {
+        return Err("Key capture requires the user's explicit consent; call request_key_capture_consent first".to_string());
+    }
+
+    // Nothing is recorded unless the indicator is showing
+    let indicator = show_key_capture_indicator(&app, &hook)?;
+    let mut state = hook.state.lock().unwrap();
+    ensure_input_hook(&app, &hook, &mut state);
+    state.capturing = true;
+    state.buffer.clear();
+    let previous = state.indicator.replace(indicator);
+    drop(state);
+    close_window(&app, previous);
+    app.emit("key-capture-active", true).ok();
+    Ok("Key capture started".to_string())
+}
+
+// Returns everything typed since start_key_capture and forgets it
+#[tauri::command]
+fn stop_key_capture(app: tauri::AppHandle, hook: tauri::State<'_, InputHook>) -> String {
+    let mut state = hook.state.lock().unwrap();
+    state.capturing = false;
+    let indicator = state.indicator.take();
+    let typed = std::mem::take(&mut state.buffer);
+    drop(state);
+    close_window(&app, indicator);
+    app.emit("key-capture-active", false).ok();
+    typed
+}
+
+// Microseconds between sending a 1px synthetic mouse move and the global
+// hook seeing it. Approximate: it includes hook dispatch overhead, real mouse
+// movement at the same moment can cut it short, and what the OS counts as
+// "delivered" differs by platform. Use it to compare machines or pick delays
+// for typing and clicks, not as an exact figure.
+#[tauri::command(async)]
+fn measure_input_latency(app: tauri::AppHandle, hook: tauri::State<'_, InputHook>, limiter: tauri::State<'_, InputRateLimiter>) -> Result<u64, String> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let just_started = {
+        let mut state = hook.state.lock().unwrap();
+        let just_started = ensure_input_hook(&app, &hook, &mut state);
+        state.latency_probe = Some(sender);
+        just_started
+    };
+    if just_started {
+        // Give the new hook time to install before probing it
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    let sent = std::time::Instant::now();
+    enigo.move_mouse(1, 0, Coordinate::Rel).map_err(describe_enigo_error)?;
+    let observed = receiver.recv_timeout(std::time::Duration::from_secs(2));
+    hook.state.lock().unwrap().latency_probe = None;
+    limiter.wait();
+    enigo.move_mouse(-1, 0, Coordinate::Rel).map_err(describe_enigo_error)?;
+
+    let observed = observed.map_err(|_| "The synthetic input was never observed; the input hook may lack permission".to_string())?;
+    Ok(observed.saturating_duration_since(sent).as_micros() as u64)
+}
+
+// Global hotkey commands
+// true if this app or any other application already holds the accelerator.
+// Other apps' registrations are invisible, so the shortcut is briefly
+// registered and released again to probe for a collision.
+#[tauri::command(async)]
+fn is_hotkey_registered(app: tauri::AppHandle, accelerator: String) -> Result<bool, String> {
+    use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+    let shortcut: Shortcut = accelerator.parse()
+        .map_err(|e| format!("Invalid accelerator {}: {}", accelerator, e))?;
+    let global_shortcut = app.global_shortcut();
+
+    if global_shortcut.is_registered(shortcut) {
+        return Ok(true);
+    }
+
+    match global_shortcut.register(shortcut) {
+        Ok(()) => {
+            global_shortcut.unregister(shortcut).ok();
+            Ok(false)
+        }
+        Err(_) => Ok(true),
+    }
+}
+
+// Clipboard commands
+#[derive(Default)]
+struct ClipboardWatch {
+    running: Arc<AtomicBool>,
+}
+
+#[tauri::command]
+fn start_clipboard_watch(app: tauri::AppHandle, watch: tauri::State<'_, ClipboardWatch>) -> Result<String, String> {
+    if watch.running.swap(true, Ordering::SeqCst) {
+        return Ok("Clipboard watch already running".to_string());
+    }
+
+    let running = watch.running.clone();
+    std::thread::spawn(move || {
+        let mut clipboard = match arboard::Clipboard::new() {
+            Ok(clipboard) => clipboard,
+            Err(_) => {
+                running.store(false, Ordering::SeqCst);
+                return;
+            }
+        };
+
+        // Polling is portable; only changes made after the watch starts are reported
+        let mut last = clipboard.get_text().ok();
+        while running.load(Ordering::SeqCst) {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            let current = clipboard.get_text().ok();
+            if current.is_some() && current != last {
+                app.emit("clipboard-changed", current.clone()).ok();
+                last = current;
+            }
+        }
+    });
+
+    Ok("Clipboard watch started".to_string())
+}
+
+#[tauri::command]
+fn stop_clipboard_watch(watch: tauri::State<'_, ClipboardWatch>) -> String {
+    watch.running.store(false, Ordering::SeqCst);
+    "Clipboard watch stopped".to_string()
+}
+
+// Which of "text", "html", "rtf", "image" and "file-list" the clipboard
+// currently holds. arboard can't read RTF, so that one is asked of the OS.
+#[tauri::command]
+fn clipboard_formats() -> Result<Vec<String>, String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    let mut formats = Vec::new();
+    if clipboard.get().text().is_ok() {
+        formats.push("text".to_string());
+    }
+    if clipboard.get().html().is_ok() {
+        formats.push("html".to_string());
+    }
+    if clipboard_has_rtf() {
+        formats.push("rtf".to_string());
+    }
+    if clipboard.get().image().is_ok() {
+        formats.push("image".to_string());
+    }
+    if clipboard.get().file_list().is_ok_and(|files| !files.is_empty()) {
+        formats.push("file-list".to_string());
+    }
+    Ok(formats)
+}
+
+fn clipboard_has_rtf() -> bool {
+    #[cfg(target_os = "windows")]
+    unsafe {
+        use windows_sys::Win32::System::DataExchange::{IsClipboardFormatAvailable, RegisterClipboardFormatW};
+
+        let name: Vec<u16> = "Rich Text Format".encode_utf16().chain(std::iter::once(0)).collect();
+        let format = RegisterClipboardFormatW(name.as_ptr());
+        format != 0 && IsClipboardFormatAvailable(format) != 0
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("osascript")
+            .args(["-e", "clipboard info"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains("«class RTF »"))
+            .unwrap_or(false)
+    }
+
+    // wl-paste on Wayland, xclip on X11
+    #[cfg(target_os = "linux")]
+    {
+        let output = if std::env::var("WAYLAND_DISPLAY").is_ok() {
+            Command::new("wl-paste").arg("--list-types").output()
+        } else {
+            Command::new("xclip").args(["-selection", "clipboard", "-t", "TARGETS", "-o"]).output()
+        };
+        output
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .any(|target| target == "text/rtf" || target == "application/rtf")
+            })
+            .unwrap_or(false)
+    }
+}
+
+// HTML as copied from browsers and office apps, keeping links and
+// formatting that a text read drops. None if there is no HTML on it.
+#[tauri::command]
+fn clipboard_read_html() -> Result<Option<String>, String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    Ok(clipboard.get().html().ok())
+}
+
+// Selected text in the foreground app.
+// macOS reads AXSelectedText from the focused element (needs Accessibility
+// permission) and Linux reads the PRIMARY selection; both leave the clipboard
+// untouched. Windows, and macOS apps that don't expose AXSelectedText, fall
+// back to sending a copy shortcut and restoring the previous clipboard text.
+#[tauri::command(async)]
+fn get_selected_text(limiter: tauri::State<'_, InputRateLimiter>) -> Result<String, String> {
+    #[cfg(target_os = "macos")]
+    {
+        let output = Command::new("osascript")
+            .args(["-e", "tell application \"System Events\" to tell (first process whose frontmost is true) to get value of attribute \"AXSelectedText\" of (value of attribute \"AXFocusedUIElement\")"])
+            .output()
+            .map_err(|e| e.to_string())?;
+        let text = String::from_utf8_lossy(&output.stdout).trim_end_matches('\n').to_string();
+        if output.status.success() && !text.is_empty() && text != "missing value" {
+            return Ok(text);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        use arboard::{GetExtLinux, LinuxClipboardKind};
+        let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+        if let Ok(text) = clipboard.get().clipboard(LinuxClipboardKind::Primary).text() {
+            return Ok(text);
+        }
+    }
+
+    copy_selection_via_clipboard(&limiter)
+}
+
+// Prints "x y width height" around every selected line, or nothing. The
+// process opts into DPI awareness so UI Automation reports physical pixels.
+#[cfg(target_os = "windows")]
+const SELECTION_BOUNDS_SCRIPT: &str = r#"
+$ErrorActionPreference = 'Stop'
+Add-Type -MemberDefinition '[DllImport("user32.dll")] public static extern bool SetProcessDPIAware();' -Name Dpi -Namespace Pal
+[Pal.Dpi]::SetProcessDPIAware() | Out-Null
+Add-Type -AssemblyName UIAutomationClient, UIAutomationTypes
+$element = [System.Windows.Automation.AutomationElement]::FocusedElement
+$pattern = $null
+if (-not $element -or -not $element.TryGetCurrentPattern([System.Windows.Automation.TextPattern]::Pattern, [ref]$pattern)) { exit }
+$rects = @($pattern.GetSelection() | ForEach-Object { $_.GetBoundingRectangles() } | Where-Object { $_.Width -gt 0 })
+if ($rects.Count -eq 0) { exit }
+$left = ($rects | Measure-Object -Property Left -Minimum).Minimum
+$top = ($rects | Measure-Object -Property Top -Minimum).Minimum
+$right = ($rects | Measure-Object -Property Right -Maximum).Maximum
+$bottom = ($rects | Measure-Object -Property Bottom -Maximum).Maximum
+'{0} {1} {2} {3}' -f [int]$left, [int]$top, [int]($right - $left), [int]($bottom - $top)
+"#;
+
+// Screen rectangle around the focused control's text selection, in physical
+// pixels, for placing a popup next to it. Windows asks UI Automation through
+// PowerShell. None when nothing is selected, the control doesn't expose its
+// text, or on other platforms.
+#[tauri::command(async)]
+fn get_selection_bounds() -> Result<Option<ScreenRegion>, String> {
+    #[cfg(target_os = "windows")]
+    {
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", SELECTION_BOUNDS_SCRIPT])
+            .output()
+            .map_err(|e| format!("Failed to run powershell: {}", e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let numbers: Vec<i64> = stdout.split_whitespace().filter_map(|part| part.parse().ok()).collect();
+        match numbers[..] {
+            [x, y, width, height] if output.status.success() && width > 0 && height > 0 => Ok(Some(ScreenRegion {
+                x: x as i32,
+                y: y as i32,
+                width: width as u32,
+                height: height as u32,
+            })),
+            _ => Ok(None),
+        }
     }
+
+    #[cfg(not(target_os = "windows"))]
+    Ok(None)
 }
 
+// Screen position of the text caret, in physical pixels
 #[derive(serde::Serialize)]
-struct PlatformInfo {
-    os: String,
-    arch: String,
-    family: String,
+struct CaretPosition {
+    x: i32,
+    y: i32,
+    height: u32,
 }
 
-// Mouse control commands
-#[tauri::command]
-fn mouse_move(x: i32, y: i32) -> Result<String, String> {
-    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
-    enigo.move_mouse(x, y, Coordinate::Abs).map_err(|e| e.to_string())?;
-    Ok(format!("Moved mouse to ({}, {})", x, y))
+// Prints "x y height" for the caret in the focused control, or nothing.
+// The managed UI Automation client has no GetCaretRange, but the collapsed
+// selection is the caret; it has no width, so it's widened to the character
+// after it and the left edge used.
+#[cfg(target_os = "windows")]
+const CARET_POSITION_SCRIPT: &str = r#"
+$ErrorActionPreference = 'Stop'
+Add-Type -MemberDefinition '[DllImport("user32.dll")] public static extern bool SetProcessDPIAware();' -Name Dpi -Namespace Pal
+[Pal.Dpi]::SetProcessDPIAware() | Out-Null
+Add-Type -AssemblyName UIAutomationClient, UIAutomationTypes
+$element = [System.Windows.Automation.AutomationElement]::FocusedElement
+$pattern = $null
+if (-not $element -or -not $element.TryGetCurrentPattern([System.Windows.Automation.TextPattern]::Pattern, [ref]$pattern)) { exit }
+$range = @($pattern.GetSelection())[0]
+if (-not $range) { exit }
+$rects = @($range.GetBoundingRectangles() | Where-Object { $_.Height -gt 0 })
+if ($rects.Count -eq 0) {
+  $range = $range.Clone()
+  $range.ExpandToEnclosingUnit([System.Windows.Automation.Text.TextUnit]::Character)
+  $rects = @($range.GetBoundingRectangles() | Where-Object { $_.Height -gt 0 })
 }
+if ($rects.Count -eq 0) { exit }
+'{0} {1} {2}' -f [int]$rects[0].Left, [int]$rects[0].Top, [int]$rects[0].Height
+"#;
 
-#[tauri::command]
-fn mouse_click(button: String) -> Result<String, String> {
-    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
-    let mouse_button = match button.to_lowercase().as_str() {
-        "left" => Button::Left,
-        "right" => Button::Right,
-        "middle" => Button::Middle,
-        _ => return Err(format!("Invalid button: {}. Use 'left', 'right', or 'middle'", button)),
-    };
-    enigo.button(mouse_button, Direction::Click).map_err(|e| e.to_string())?;
-    Ok(format!("Clicked {} mouse button", button))
+// Where the text caret is in the focused control, for showing completions
+// right at it. Windows first asks the foreground thread for its system caret,
+// which classic edit controls use, then falls back to UI Automation through
+// PowerShell for browsers and modern apps. None when there's no caret or on
+// other platforms.
+#[tauri::command(async)]
+fn get_caret_position() -> Result<Option<CaretPosition>, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::POINT;
+        use windows_sys::Win32::Graphics::Gdi::ClientToScreen;
+        use windows_sys::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetGUIThreadInfo, GetWindowThreadProcessId, GUITHREADINFO};
+
+        unsafe {
+            let thread = GetWindowThreadProcessId(GetForegroundWindow(), std::ptr::null_mut());
+            let mut info: GUITHREADINFO = std::mem::zeroed();
+            info.cbSize = std::mem::size_of::<GUITHREADINFO>() as u32;
+            if thread != 0 && GetGUIThreadInfo(thread, &mut info) != 0 && !info.hwndCaret.is_null() {
+                let mut point = POINT { x: info.rcCaret.left, y: info.rcCaret.top };
+                let height = info.rcCaret.bottom - info.rcCaret.top;
+                if height > 0 && ClientToScreen(info.hwndCaret, &mut point) != 0 {
+                    return Ok(Some(CaretPosition { x: point.x, y: point.y, height: height as u32 }));
+                }
+            }
+        }
+
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", CARET_POSITION_SCRIPT])
+            .output()
+            .map_err(|e| format!("Failed to run powershell: {}", e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let numbers: Vec<i64> = stdout.split_whitespace().filter_map(|part| part.parse().ok()).collect();
+        match numbers[..] {
+            [x, y, height] if output.status.success() && height > 0 => Ok(Some(CaretPosition {
+                x: x as i32,
+                y: y as i32,
+                height: height as u32,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    Ok(None)
 }
 
-#[tauri::command]
-fn mouse_scroll(amount: i32) -> Result<String, String> {
-    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
-    // Positive = scroll down, negative = scroll up
-    enigo.scroll(amount, enigo::Axis::Vertical).map_err(|e| e.to_string())?;
-    Ok(format!("Scrolled mouse by {}", amount))
+fn copy_selection_via_clipboard(limiter: &InputRateLimiter) -> Result<String, String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    let previous = clipboard.get_text().ok();
+    clipboard.clear().map_err(|e| e.to_string())?;
+
+    press_clipboard_shortcut('c', limiter)?;
+
+    // The target app updates the clipboard asynchronously
+    let mut selected = None;
+    for _ in 0..20 {
+        std::thread::sleep(std::time::Duration::from_millis(25));
+        if let Ok(text) = clipboard.get_text() {
+            selected = Some(text);
+            break;
+        }
+    }
+
+    match previous {
+        Some(text) => clipboard.set_text(text).map_err(|e| e.to_string())?,
+        None => clipboard.clear().map_err(|e| e.to_string())?,
+    }
+
+    selected.ok_or_else(|| "No text is selected".to_string())
 }
 
-#[tauri::command]
-fn get_mouse_position() -> Result<(i32, i32), String> {
-    let enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
-    let (x, y) = enigo.location().map_err(|e| e.to_string())?;
-    Ok((x, y))
+// Cmd+<letter> on macOS, Ctrl+<letter> elsewhere
+fn press_clipboard_shortcut(letter: char, limiter: &InputRateLimiter) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let modifier = Key::Meta;
+    #[cfg(not(target_os = "macos"))]
+    let modifier = Key::Control;
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.key(modifier, Direction::Press).map_err(describe_enigo_error)?;
+    limiter.wait();
+    let pressed = enigo.key(Key::Unicode(letter), Direction::Click);
+    limiter.wait();
+    enigo.key(modifier, Direction::Release).map_err(describe_enigo_error)?;
+    pressed.map_err(describe_enigo_error)
 }
 
-// Keyboard control commands
-#[tauri::command]
-fn keyboard_type(text: String) -> Result<String, String> {
-    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
-    enigo.text(&text).map_err(|e| e.to_string())?;
-    Ok(format!("Typed: {}", text))
+// Paste text instead of typing it. keyboard_type sends characters one by one
+// and some platforms garble emoji and CJK text that way; a paste delivers the
+// exact string. The previous clipboard text is put back afterwards.
+#[tauri::command(async)]
+fn insert_unicode_text(text: String, limiter: tauri::State<'_, InputRateLimiter>, automation: tauri::State<'_, Automation>) -> Result<String, String> {
+    paste_text(&text, &limiter, &automation, automation.token())?;
+    Ok(format!("Inserted {} characters", text.chars().count()))
+}
+
+#[derive(serde::Serialize)]
+struct TypeabilityReport {
+    ok: bool,
+    // Each one listed once, in order of first appearance
+    problematic_chars: Vec<char>,
 }
 
+// Whether keyboard_type can produce every character of `text` with real
+// keys on the focused app's keyboard layout. Windows asks the layout
+// directly (characters it has no key for are flagged); other platforms
+// have no cheap layout query, so everything outside printable ASCII is
+// flagged. Send flagged text with insert_unicode_text instead.
 #[tauri::command]
-fn keyboard_press(key: String) -> Result<String, String> {
-    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+fn can_type_string(text: String) -> TypeabilityReport {
+    #[cfg(target_os = "windows")]
+    let typeable = {
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetKeyboardLayout, VkKeyScanExW};
+        use windows_sys::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
 
-    let enigo_key = match key.to_lowercase().as_str() {
-        "enter" | "return" => Key::Return,
-        "tab" => Key::Tab,
-        "escape" | "esc" => Key::Escape,
-        "backspace" => Key::Backspace,
-        "delete" | "del" => Key::Delete,
-        "up" | "uparrow" => Key::UpArrow,
-        "down" | "downarrow" => Key::DownArrow,
-        "left" | "leftarrow" => Key::LeftArrow,
-        "right" | "rightarrow" => Key::RightArrow,
-        "home" => Key::Home,
-        "end" => Key::End,
-        "pageup" => Key::PageUp,
-        "pagedown" => Key::PageDown,
-        "space" => Key::Space,
-        "shift" => Key::Shift,
-        "control" | "ctrl" => Key::Control,
-        "alt" => Key::Alt,
-        "meta" | "windows" | "cmd" | "command" => Key::Meta,
-        _ => return Err(format!("Unsupported key: {}. Use special key names like 'enter', 'tab', 'escape', etc.", key)),
+        let layout = unsafe { GetKeyboardLayout(GetWindowThreadProcessId(GetForegroundWindow(), std::ptr::null_mut())) };
+        move |c: char| {
+            let mut units = [0u16; 2];
+            // Characters outside the BMP would need two key events, which no layout has
+            c.encode_utf16(&mut units).len() == 1 && unsafe { VkKeyScanExW(units[0], layout) } != -1
+        }
     };
 
-    enigo.key(enigo_key, Direction::Click).map_err(|e| e.to_string())?;
-    Ok(format!("Pressed key: {}", key))
-}
-
-#[tauri::command]
-fn keyboard_shortcut(keys: Vec<String>) -> Result<String, String> {
-    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+    #[cfg(not(target_os = "windows"))]
+    let typeable = |c: char| c.is_ascii_graphic() || c == ' ';
 
-    // Press all keys down
-    for key in &keys {
-        let enigo_key = match key.to_lowercase().as_str() {
-            "shift" => Key::Shift,
-            "control" | "ctrl" => Key::Control,
-            "alt" => Key::Alt,
-            "meta" | "windows" | "cmd" | "command" => Key::Meta,
-            single if single.len() == 1 => {
-                enigo.text(single).map_err(|e| e.to_string())?;
-                continue;
-            }
-            _ => return Err(format!("Unsupported key in shortcut: {}", key)),
-        };
-        enigo.key(enigo_key, Direction::Press).map_err(|e| e.to_string())?;
+    let mut problematic_chars: Vec<char> = Vec::new();
+    for c in text.chars() {
+        let control = matches!(c, '\n' | '\r' | '\t');
+        if !control && !typeable(c) && !problematic_chars.contains(&c) {
+            problematic_chars.push(c);
+        }
     }
+    TypeabilityReport { ok: problematic_chars.is_empty(), problematic_chars }
+}
 
-    // Release all keys
-    for key in keys.iter().rev() {
-        let enigo_key = match key.to_lowercase().as_str() {
-            "shift" => Key::Shift,
-            "control" | "ctrl" => Key::Control,
-            "alt" => Key::Alt,
-            "meta" | "windows" | "cmd" | "command" => Key::Meta,
-            _ => continue,
-        };
-        enigo.key(enigo_key, Direction::Release).map_err(|e| e.to_string())?;
-    }
+// Clicks at (x, y) to focus a field, then pastes text into it. Much faster
+// than typing long text, and the clipboard is restored afterwards.
+#[tauri::command(async)]
+fn paste_at(
+    x: i32,
+    y: i32,
+    text: String,
+    limiter: tauri::State<'_, InputRateLimiter>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<String, String> {
+    let token = automation.token();
+    let mut enigo = Enigo::new(&Settings::default()).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.move_mouse(x, y, Coordinate::Abs).map_err(describe_enigo_error)?;
+    limiter.wait();
+    enigo.button(Button::Left, Direction::Click).map_err(describe_enigo_error)?;
 
-    Ok(format!("Executed keyboard shortcut: {}", keys.join("+")))
+    // Give the target field time to take focus before pasting
+    automation.sleep(token, std::time::Duration::from_millis(100))?;
+
+    paste_text(&text, &limiter, &automation, token)?;
+    Ok(format!("Pasted {} characters at ({}, {})", text.chars().count(), x, y))
 }
 
-// Window management commands
-#[tauri::command]
-fn toggle_main_window(app: tauri::AppHandle) -> Result<String, String> {
-    let main_window = app.get_webview_window("main")
-        .ok_or("Main window not found")?;
-    let bubble_window = app.get_webview_window("bubble")
-        .ok_or("Bubble window not found")?;
+fn paste_text(text: &str, limiter: &InputRateLimiter, automation: &Automation, token: u64) -> Result<(), String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    let previous = clipboard.get_text().ok();
+    clipboard.set_text(text).map_err(|e| e.to_string())?;
 
-    if main_window.is_visible().unwrap_or(false) {
-        // Hide main, show bubble
-        main_window.hide().map_err(|e| e.to_string())?;
-        bubble_window.show().map_err(|e| e.to_string())?;
-        bubble_window.set_focus().ok();
-        Ok("Switched to bubble mode".to_string())
-    } else {
-        // Show main, keep bubble visible
-        main_window.show().map_err(|e| e.to_string())?;
-        main_window.set_focus().map_err(|e| e.to_string())?;
-        Ok("Switched to main window".to_string())
+    let pasted = automation.check(token).and_then(|_| press_clipboard_shortcut('v', limiter));
+    // The target app reads the clipboard asynchronously after the shortcut;
+    // the user's clipboard is put back even if this is cancelled
+    let waited = automation.sleep(token, std::time::Duration::from_millis(200));
+
+    match previous {
+        Some(previous) => clipboard.set_text(previous).map_err(|e| e.to_string())?,
+        None => clipboard.clear().map_err(|e| e.to_string())?,
     }
+
+    pasted.and(waited)
 }
 
+// X11/Wayland PRIMARY selection (what middle-click pastes), separate from the
+// regular clipboard. Other platforms have no primary selection.
 #[tauri::command]
-fn minimize_to_bubble(app: tauri::AppHandle) -> Result<String, String> {
-    let main_window = app.get_webview_window("main")
-        .ok_or("Main window not found")?;
-    let bubble_window = app.get_webview_window("bubble")
-        .ok_or("Bubble window not found")?;
-
-    main_window.hide().map_err(|e| e.to_string())?;
-    bubble_window.show().map_err(|e| e.to_string())?;
-    bubble_window.set_focus().ok();
+fn linux_primary_selection_read() -> Result<String, String> {
+    #[cfg(target_os = "linux")]
+    {
+        use arboard::{GetExtLinux, LinuxClipboardKind};
+        let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+        clipboard.get().clipboard(LinuxClipboardKind::Primary).text().map_err(|e| e.to_string())
+    }
 
-    Ok("Minimized to bubble".to_string())
+    #[cfg(not(target_os = "linux"))]
+    Err("The primary selection only exists on Linux".to_string())
 }
 
 #[tauri::command]
-fn expand_from_bubble(app: tauri::AppHandle) -> Result<String, String> {
-    let main_window = app.get_webview_window("main")
-        .ok_or("Main window not found")?;
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+fn linux_primary_selection_write(text: String) -> Result<String, String> {
+    #[cfg(target_os = "linux")]
+    {
+        use arboard::{LinuxClipboardKind, SetExtLinux};
 
-    main_window.show().map_err(|e| e.to_string())?;
-    main_window.set_focus().map_err(|e| e.to_string())?;
+        // The selection is served by this process and disappears when the
+        // last arboard handle is dropped, so keep the owner alive
+        static OWNER: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+        let mut owner = OWNER.lock().unwrap();
+        if owner.is_none() {
+            *owner = Some(arboard::Clipboard::new().map_err(|e| e.to_string())?);
+        }
+        if let Some(clipboard) = owner.as_mut() {
+            clipboard.set().clipboard(LinuxClipboardKind::Primary).text(text.clone()).map_err(|e| e.to_string())?;
+        }
+        Ok(format!("Set primary selection ({} characters)", text.chars().count()))
+    }
 
-    Ok("Expanded to main window".to_string())
+    #[cfg(not(target_os = "linux"))]
+    Err("The primary selection only exists on Linux".to_string())
 }
 
 // Browser automation with Playwright
@@ -230,13 +6247,97 @@ fn browser_open(url: String) -> Result<String, String> {
     }
 }
 
+// Caps how many Playwright scripts run at once; extra calls wait for a slot
+struct PlaywrightLimit {
+    slots: Mutex<PlaywrightSlots>,
+    freed: std::sync::Condvar,
+}
+
+struct PlaywrightSlots {
+    limit: usize,
+    running: usize,
+}
+
+impl Default for PlaywrightLimit {
+    fn default() -> Self {
+        PlaywrightLimit {
+            slots: Mutex::new(PlaywrightSlots { limit: 3, running: 0 }),
+            freed: std::sync::Condvar::new(),
+        }
+    }
+}
+
+// Holds one slot; dropping it lets the next queued script start
+struct PlaywrightPermit<'a>(&'a PlaywrightLimit);
+
+impl PlaywrightLimit {
+    fn acquire(&self) -> PlaywrightPermit<'_> {
+        let mut slots = self.slots.lock().unwrap();
+        while slots.running >= slots.limit {
+            slots = self.freed.wait(slots).unwrap();
+        }
+        slots.running += 1;
+        PlaywrightPermit(self)
+    }
+}
+
+impl Drop for PlaywrightPermit<'_> {
+    fn drop(&mut self) {
+        self.0.slots.lock().unwrap().running -= 1;
+        self.0.freed.notify_one();
+    }
+}
+
 #[tauri::command]
-fn run_playwright_script(script: String) -> Result<String, String> {
+fn set_playwright_concurrency(n: usize, limit: tauri::State<'_, PlaywrightLimit>) -> Result<String, String> {
+    if n == 0 {
+        return Err("Concurrency must be at least 1".to_string());
+    }
+    limit.slots.lock().unwrap().limit = n;
+    // A higher limit may let several queued scripts start
+    limit.freed.notify_all();
+    Ok(format!("Playwright concurrency set to {}", n))
+}
+
+// Runs on the thread pool so queued scripts don't block the main thread
+#[tauri::command(async)]
+fn run_playwright_script(
+    script: String,
+    limit: tauri::State<'_, PlaywrightLimit>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<String, String> {
+    run_node_script(script, &limit, &automation)
+}
+
+// Scripts wrap their result in these markers for run_playwright_json, e.g.
+// console.log('<<<PAL_JSON'); console.log(JSON.stringify(items)); console.log('PAL_JSON>>>');
+const PLAYWRIGHT_JSON_START: &str = "<<<PAL_JSON";
+const PLAYWRIGHT_JSON_END: &str = "PAL_JSON>>>";
+
+#[tauri::command(async)]
+fn run_playwright_json(
+    script: String,
+    limit: tauri::State<'_, PlaywrightLimit>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<serde_json::Value, String> {
+    let stdout = run_node_script(script, &limit, &automation)?;
+
+    // The last block wins so logging before the result can't confuse it
+    let block = stdout
+        .rfind(PLAYWRIGHT_JSON_START)
+        .map(|start| &stdout[start + PLAYWRIGHT_JSON_START.len()..])
+        .and_then(|rest| rest.find(PLAYWRIGHT_JSON_END).map(|end| &rest[..end]))
+        .ok_or_else(|| format!("No JSON block found in script output:\n{}", stdout))?;
+
+    serde_json::from_str(block.trim())
+        .map_err(|e| format!("Invalid JSON block ({}) in script output:\n{}", e, stdout))
+}
+
+fn run_node_script(script: String, limit: &PlaywrightLimit, automation: &Automation) -> Result<String, String> {
+    let _permit = limit.acquire();
+
     // Run a Node.js Playwright script
-    let output = Command::new("node")
-        .arg("-e")
-        .arg(script)
-        .output()
+    let output = automation.output(Command::new("node").arg("-e").arg(script))
         .map_err(|e| format!("Failed to run Playwright script: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
@@ -249,33 +6350,631 @@ fn run_playwright_script(script: String) -> Result<String, String> {
     }
 }
 
+// Persistent Playwright sessions: a node helper keeps one browser, context
+// and page open and runs each playwright_exec script against them, so later
+// steps reuse cookies and logins instead of launching a fresh browser. A
+// session ends with playwright_close_session, or when the app exits and the
+// helper's stdin closes.
+//
+// Scripts are async function bodies with `browser`, `context` and `page` in
+// scope; their return value (JSON-serializable) and console.log output come
+// back. Requests and replies are one JSON document per line.
+const PLAYWRIGHT_SESSION_HELPER: &str = r#"
+const readline = require('readline');
+const { chromium } = require('playwright');
+const send = (message) => process.stdout.write(JSON.stringify(message) + '\n');
+let logs = [];
+console.log = (...args) => logs.push(args.map(String).join(' '));
+const AsyncFunction = Object.getPrototypeOf(async function () {}).constructor;
+
+(async () => {
+  let browser, context, page;
+  try {
+    browser = await chromium.launch({ headless: process.argv[1] !== 'headed' });
+    context = await browser.newContext();
+    page = await context.newPage();
+  } catch (error) {
+    send({ ok: false, error: String(error) });
+    process.exit(1);
+  }
+  send({ ok: true });
+
+  for await (const line of readline.createInterface({ input: process.stdin })) {
+    logs = [];
+    try {
+      const result = await new AsyncFunction('browser', 'context', 'page', JSON.parse(line))(browser, context, page);
+      send({ ok: true, result: result === undefined ? null : result, output: logs.join('\n') });
+    } catch (error) {
+      send({ ok: false, error: String((error && error.stack) || error), output: logs.join('\n') });
+    }
+  }
+  await browser.close();
+})();
+"#;
+
+struct PlaywrightSession {
+    child: std::process::Child,
+    // Taken on close; the helper exits once its stdin is closed
+    stdin: Option<std::process::ChildStdin>,
+    stdout: std::io::BufReader<std::process::ChildStdout>,
+}
+
+// Also covers sessions dropped by cancel_all or a failed launch
+impl Drop for PlaywrightSession {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+impl PlaywrightSession {
+    fn reply(&mut self) -> Result<serde_json::Value, String> {
+        use std::io::BufRead;
+
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Err("Playwright session ended unexpectedly".to_string());
+        }
+        serde_json::from_str(&line).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Default)]
+struct PlaywrightSessions {
+    sessions: Mutex<std::collections::HashMap<u32, Arc<Mutex<PlaywrightSession>>>>,
+    next_id: std::sync::atomic::AtomicU32,
+}
+
+#[derive(serde::Serialize)]
+struct PlaywrightStepResult {
+    result: serde_json::Value,
+    // Everything the script passed to console.log
+    output: String,
+}
+
+// headless defaults to true
+#[tauri::command(async)]
+fn playwright_start_session(
+    headless: Option<bool>,
+    sessions: tauri::State<'_, PlaywrightSessions>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<u32, String> {
+    let mut child = Command::new("node")
+        .arg("-e")
+        .arg(PLAYWRIGHT_SESSION_HELPER)
+        .arg(if headless.unwrap_or(true) { "headless" } else { "headed" })
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start Playwright session: {}", e))?;
+    let stdin = child.stdin.take().ok_or("Failed to open Playwright session input")?;
+    let stdout = child.stdout.take().ok_or("Failed to open Playwright session output")?;
+    let mut session = PlaywrightSession { child, stdin: Some(stdin), stdout: std::io::BufReader::new(stdout) };
+
+    // The helper says whether the browser launched before taking any scripts
+    let ready = session.reply();
+    if !ready.as_ref().is_ok_and(|reply| reply["ok"] == true) {
+        let error = match ready {
+            Ok(reply) => reply["error"].as_str().unwrap_or("unknown error").to_string(),
+            Err(e) => e,
+        };
+        return Err(format!("Failed to launch browser: {}", error));
+    }
+
+    let id = sessions.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+    automation.children.lock().unwrap().insert(session.child.id());
+    sessions.sessions.lock().unwrap().insert(id, Arc::new(Mutex::new(session)));
+    Ok(id)
+}
+
+// Steps sent to one session run one at a time, in the order they arrive
+#[tauri::command(async)]
+fn playwright_exec(session_id: u32, script: String, sessions: tauri::State<'_, PlaywrightSessions>) -> Result<PlaywrightStepResult, String> {
+    use std::io::Write;
+
+    let session = sessions.sessions.lock().unwrap().get(&session_id).cloned()
+        .ok_or(format!("Playwright session not found: {}", session_id))?;
+    let mut session = session.lock().unwrap();
+
+    let request = serde_json::to_string(&script).map_err(|e| e.to_string())?;
+    let stdin = session.stdin.as_mut().ok_or("Playwright session is closed")?;
+    writeln!(stdin, "{}", request)
+        .and_then(|_| stdin.flush())
+        .map_err(|e| format!("Playwright session ended: {}", e))?;
+
+    let reply = session.reply()?;
+    if reply["ok"] == true {
+        Ok(PlaywrightStepResult {
+            result: reply["result"].clone(),
+            output: reply["output"].as_str().unwrap_or_default().to_string(),
+        })
+    } else {
+        Err(format!("Playwright error: {}", reply["error"].as_str().unwrap_or("unknown error")))
+    }
+}
+
+#[tauri::command(async)]
+fn playwright_close_session(
+    session_id: u32,
+    sessions: tauri::State<'_, PlaywrightSessions>,
+    automation: tauri::State<'_, Automation>,
+) -> Result<String, String> {
+    let session = sessions.sessions.lock().unwrap().remove(&session_id)
+        .ok_or(format!("Playwright session not found: {}", session_id))?;
+    // Waits for a running step to finish first
+    let mut session = session.lock().unwrap();
+    automation.children.lock().unwrap().remove(&session.child.id());
+    session.stdin.take();
+
+    // Give the helper a moment to close the browser cleanly
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while session.child.try_wait().map_err(|e| e.to_string())?.is_none() {
+        if std::time::Instant::now() >= deadline {
+            session.child.kill().ok();
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    Ok(format!("Closed Playwright session {}", session_id))
+}
+
+// Settings persistence
+// The options set through set_default_shell, set_input_rate_limit,
+// set_toggle_debounce_ms and set_playwright_concurrency, kept in
+// settings.json in the app config dir and applied again at startup.
+// Missing fields keep their defaults, so older files still load.
+const SETTINGS_FORMAT_VERSION: u32 = 1;
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct SettingsFile {
+    version: u32,
+    default_shell: Option<String>,
+    // Events per second, 0 for unlimited
+    input_rate_limit: Option<u32>,
+    toggle_debounce_ms: Option<u64>,
+    playwright_concurrency: Option<usize>,
+}
+
+fn settings_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join("settings.json"))
+}
+
+#[tauri::command]
+fn save_settings(app: tauri::AppHandle) -> Result<String, String> {
+    let interval = app.state::<InputRateLimiter>().state.lock().unwrap().interval;
+    let file = SettingsFile {
+        version: SETTINGS_FORMAT_VERSION,
+        default_shell: app.state::<ShellConfig>().default_shell.lock().unwrap().clone(),
+        input_rate_limit: Some(interval.map_or(0, |interval| (1.0 / interval.as_secs_f64()).round() as u32)),
+        toggle_debounce_ms: Some(app.state::<ToggleDebounce>().state.lock().unwrap().window.as_millis() as u64),
+        playwright_concurrency: Some(app.state::<PlaywrightLimit>().slots.lock().unwrap().limit),
+    };
+
+    let path = settings_path(&app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())?;
+    Ok(format!("Saved settings to {}", path.display()))
+}
+
+// A missing file just means nothing has been saved yet
+#[tauri::command]
+fn load_settings(app: tauri::AppHandle) -> Result<String, String> {
+    let path = settings_path(&app)?;
+    let json = match std::fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok("No saved settings".to_string()),
+        Err(e) => return Err(e.to_string()),
+    };
+    let file: SettingsFile = serde_json::from_str(&json)
+        .map_err(|e| format!("Invalid settings file {}: {}", path.display(), e))?;
+    if file.version > SETTINGS_FORMAT_VERSION {
+        return Err(format!(
+            "Settings file {} uses format version {}, this app supports up to {}",
+            path.display(), file.version, SETTINGS_FORMAT_VERSION
+        ));
+    }
+
+    if let Some(shell) = file.default_shell {
+        *app.state::<ShellConfig>().default_shell.lock().unwrap() = Some(shell);
+    }
+    if let Some(events_per_second) = file.input_rate_limit {
+        app.state::<InputRateLimiter>().state.lock().unwrap().interval =
+            (events_per_second > 0).then(|| std::time::Duration::from_secs(1) / events_per_second);
+    }
+    if let Some(ms) = file.toggle_debounce_ms {
+        app.state::<ToggleDebounce>().state.lock().unwrap().window = std::time::Duration::from_millis(ms);
+    }
+    if let Some(limit) = file.playwright_concurrency.filter(|limit| *limit > 0) {
+        let playwright = app.state::<PlaywrightLimit>();
+        playwright.slots.lock().unwrap().limit = limit;
+        playwright.freed.notify_all();
+    }
+    Ok(format!("Loaded settings from {}", path.display()))
+}
+
+// Command history
+
+const COMMAND_HISTORY_LIMIT: usize = 50;
+
+// Never recorded: key capture can carry typed secrets, consent tokens and the
+// writes they approve must not outlive the approval, and the history commands
+// themselves would push the entry the user wants to replay out of position
+const UNRECORDED_COMMANDS: &[&str] = &[
+    "request_key_capture_consent",
+    "start_key_capture",
+    "stop_key_capture",
+    "request_setting_write_consent",
+    "write_setting",
+    "get_command_history",
+    "replay_command",
+];
+
+// Arguments that often carry passwords or other secrets are recorded as
+// "[redacted]", and those entries can't be replayed
+const REDACTED_ARGS: &[(&str, &[&str])] = &[
+    ("keyboard_type", &["text"]),
+    ("keyboard_type_raw", &["text"]),
+    ("keyboard_type_delayed", &["text"]),
+    ("type_at", &["text"]),
+    ("test_typing", &["text"]),
+    ("typing_test_report", &["value"]),
+    ("paste_at", &["text"]),
+    ("insert_unicode_text", &["text"]),
+    ("send_text_to_pid", &["text"]),
+    ("linux_primary_selection_write", &["text"]),
+    ("can_type_string", &["text"]),
+    ("set_env", &["value"]),
+    ("run_actions", &["actions"]),
+    ("keyboard_sequence", &["steps"]),
+    ("send_keys_to_window", &["keys"]),
+];
+
+// Recorded, but replay_command refuses them: they run arbitrary code or take
+// over the machine, so every run needs the user behind it
+const UNREPLAYABLE_COMMANDS: &[&str] = &[
+    "run_shell_command",
+    "run_shell_command_json",
+    "run_shell_command_timed",
+    "run_shell_pipeline",
+    "run_script",
+    "run_playwright_script",
+    "run_playwright_json",
+    "playwright_exec",
+    "block_user_input",
+    "set_process_priority",
+];
+
+#[derive(Clone, serde::Serialize)]
+struct CommandInvocation {
+    command: String,
+    args: serde_json::Value,
+    // Some arguments were replaced by "[redacted]"
+    redacted: bool,
+    // Milliseconds since the Unix epoch
+    invoked_at: u64,
+}
+
+#[derive(Default)]
+struct CommandHistory {
+    // Newest last; capped at COMMAND_HISTORY_LIMIT
+    entries: Mutex<std::collections::VecDeque<CommandInvocation>>,
+}
+
+// Wraps the generated handler so every frontend call is recorded before it runs
+fn record_invocations<F>(handler: F) -> impl Fn(tauri::ipc::Invoke) -> bool + Send + Sync + 'static
+where
+    F: Fn(tauri::ipc::Invoke) -> bool + Send + Sync + 'static,
+{
+    move |invoke| {
+        let command = invoke.message.command();
+        if !command.starts_with("plugin:") && !UNRECORDED_COMMANDS.contains(&command) {
+            let mut args = match invoke.message.payload() {
+                tauri::ipc::InvokeBody::Json(args) => args.clone(),
+                tauri::ipc::InvokeBody::Raw(_) => serde_json::Value::Null,
+            };
+            let mut redacted = false;
+            if let Some((_, names)) = REDACTED_ARGS.iter().find(|(name, _)| *name == command) {
+                for name in names.iter() {
+                    if let Some(value) = args.get_mut(*name) {
+                        *value = serde_json::Value::String("[redacted]".to_string());
+                        redacted = true;
+                    }
+                }
+            }
+            let history = invoke.message.webview_ref().state::<CommandHistory>();
+            let mut entries = history.entries.lock().unwrap();
+            if entries.len() == COMMAND_HISTORY_LIMIT {
+                entries.pop_front();
+            }
+            entries.push_back(CommandInvocation {
+                command: command.to_string(),
+                args,
+                redacted,
+                invoked_at: unix_millis(std::time::SystemTime::now()).unwrap_or(0),
+            });
+        }
+        handler(invoke)
+    }
+}
+
+// Most recent first, so index 0 is "the last thing I did"
+#[tauri::command]
+fn get_command_history(n: usize, history: tauri::State<'_, CommandHistory>) -> Vec<CommandInvocation> {
+    history.entries.lock().unwrap().iter().rev().take(n).cloned().collect()
+}
+
+#[tauri::command]
+fn replay_command(
+    index: usize,
+    webview: tauri::Webview,
+    history: tauri::State<'_, CommandHistory>,
+) -> Result<CommandInvocation, String> {
+    let entry = history.entries.lock().unwrap().iter().rev().nth(index).cloned()
+        .ok_or(format!("No command at history index {}", index))?;
+    if UNREPLAYABLE_COMMANDS.contains(&entry.command.as_str()) {
+        return Err(format!("{} can't be replayed; run it again directly", entry.command));
+    }
+    if entry.redacted {
+        return Err(format!("{} can't be replayed because its arguments weren't recorded", entry.command));
+    }
+
+    // Re-issue it from the calling webview so it goes through the same permission
+    // checks and argument parsing as the original call
+    let script = format!(
+        "window.__TAURI_INTERNALS__.invoke({}, {}).catch((err) => console.error('[Replay] {} failed:', err));",
+        serde_json::to_string(&entry.command).map_err(|e| e.to_string())?,
+        if entry.args.is_null() { "{}".to_string() } else { entry.args.to_string() },
+        entry.command
+    );
+    webview.eval(&script).map_err(|e| e.to_string())?;
+    Ok(entry)
+}
+
+#[derive(Clone, serde::Serialize)]
+struct FilesDropped {
+    window: String,
+    paths: Vec<String>,
+    x: f64,
+    y: f64,
+}
+
 fn main() {
     tauri::Builder::default()
+        // Must come first: a second launch hands its argv to this instance and exits
+        // before it can register hotkeys or create windows
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            if let Some(main_window) = app.get_webview_window("main") {
+                main_window.show().ok();
+                main_window.set_focus().ok();
+            }
+            app.emit("second-instance", argv).ok();
+        }))
         .plugin(tauri_plugin_shell::init())
-        .invoke_handler(tauri::generate_handler![
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .manage(ClipboardWatch::default())
+        .manage(ShellConfig::default())
+        .manage(RegionSelection::default())
+        .manage(InputRateLimiter::default())
+        .manage(ToggleDebounce::default())
+        .manage(PlaywrightLimit::default())
+        .manage(PlaywrightSessions::default())
+        .manage(InputHook::default())
+        .manage(InputBlock::default())
+        .manage(MouseSettings::default())
+        .manage(BubbleHover::default())
+        .manage(ScreenshotBaseline::default())
+        .manage(CommandHistory::default())
+        .manage(Automation::default())
+        .manage(TypingTest::default())
+        .manage(DesktopReveal::default())
+        .manage(WindowLevels::default())
+        .manage(ConsentTokens::default())
+        .manage(ScreenRecorder::default())
+        .manage(ThemeOverride::default())
+        .on_window_event(|window, event| {
+            // Every window sees the change; report it once
+            if let tauri::WindowEvent::ThemeChanged(theme) = event {
+                if window.label() == "main" {
+                    window.app_handle().emit("theme-changed", *theme == tauri::Theme::Dark).ok();
+                }
+            }
+        })
+        .invoke_handler(record_invocations(tauri::generate_handler![
             run_shell_command,
+            run_shell_command_json,
+            run_shell_command_timed,
+            run_shell_pipeline,
+            set_default_shell,
+            run_script,
+            shell_quote,
             get_platform_info,
+            get_app_info,
+            get_env,
+            get_all_env,
+            set_env,
+            get_cwd,
+            set_cwd,
+            restart_app,
+            set_input_rate_limit,
+            block_user_input,
+            cancel_all,
+            is_point_on_screen,
             mouse_move,
+            mouse_move_to_corner,
+            gesture_zoom,
             mouse_click,
             mouse_scroll,
+            mouse_scroll_pixels,
             get_mouse_position,
+            get_mouse_button_state,
+            get_mouse_sensitivity,
+            set_mouse_sensitivity,
             keyboard_type,
+            keyboard_type_raw,
+            type_at,
+            keyboard_type_delayed,
+            test_typing,
+            typing_test_report,
             keyboard_press,
+            keyboard_tap,
             keyboard_shortcut,
+            get_lock_keys,
+            set_lock_key,
+            media_key,
+            keyboard_sequence,
+            run_actions,
             browser_open,
             run_playwright_script,
+            run_playwright_json,
+            set_playwright_concurrency,
+            playwright_start_session,
+            playwright_exec,
+            playwright_close_session,
             toggle_main_window,
+            set_toggle_debounce_ms,
             minimize_to_bubble,
-            expand_from_bubble
-        ])
+            expand_from_bubble,
+            set_bubble_hover_expand,
+            resize_bubble_to_content,
+            set_webview_zoom,
+            set_window_decorations,
+            set_cursor,
+            set_window_level,
+            get_window_level,
+            list_virtual_desktops,
+            switch_virtual_desktop,
+            minimize_all_windows,
+            restore_all_windows,
+            save_window_layout,
+            restore_window_layout,
+            highlight_point,
+            get_displays,
+            get_cursor_display,
+            get_work_area,
+            get_refresh_rate,
+            logical_to_physical,
+            physical_to_logical,
+            capture_interactive_region,
+            finish_region_selection,
+            capture_all_displays,
+            hover_image,
+            click_offset_from_image,
+            ocr_screen_with_boxes,
+            click_text,
+            screenshot_baseline,
+            screenshot_diff,
+            start_screen_record,
+            stop_screen_record,
+            get_window_rect,
+            set_foreign_window_bounds,
+            apply_layout_preset,
+            window_under_cursor,
+            get_windows_at_point,
+            get_active_process,
+            drag_files_to,
+            send_keys_to_window,
+            send_text_to_pid,
+            list_installed_apps,
+            launch_app,
+            save_macro,
+            load_macro,
+            hash_file,
+            path_exists,
+            wait_for_file,
+            list_dir,
+            file_info,
+            extract_archive,
+            create_archive,
+            is_screen_locked,
+            wait_for_system_idle,
+            set_process_priority,
+            get_network_status,
+            is_dark_mode,
+            set_theme,
+            get_theme,
+            read_setting,
+            request_setting_write_consent,
+            write_setting,
+            play_sound,
+            is_hotkey_registered,
+            start_clipboard_watch,
+            stop_clipboard_watch,
+            clipboard_formats,
+            clipboard_read_html,
+            request_key_capture_consent,
+            start_key_capture,
+            stop_key_capture,
+            measure_input_latency,
+            get_selected_text,
+            get_selection_bounds,
+            get_caret_position,
+            insert_unicode_text,
+            can_type_string,
+            paste_at,
+            linux_primary_selection_read,
+            linux_primary_selection_write,
+            get_command_history,
+            replay_command,
+            save_settings,
+            load_settings
+        ]))
         .setup(|app| {
             #[cfg(debug_assertions)]
             {
                 let window = app.get_webview_window("main").unwrap();
                 window.open_devtools();
             }
+
+            // A broken settings file shouldn't keep the app from starting
+            load_settings(app.handle().clone()).ok();
+
+            // No portable OS notification exists for monitor changes, so poll the
+            // layout and emit "displays-changed" (the new get_displays list) when
+            // displays are added, removed, moved, resized or rescaled
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                let mut last = display_layout(&handle).ok();
+                loop {
+                    std::thread::sleep(std::time::Duration::from_secs(2));
+                    let current = display_layout(&handle).ok();
+                    if current.is_some() && current != last {
+                        handle.emit("displays-changed", current.clone()).ok();
+                        last = current;
+                    }
+                }
+            });
+
+            // Report files dropped on either window, with the drop point in that window's
+            // physical pixels so the frontend can tell which zone received them
+            for label in ["main", "bubble"] {
+                if let Some(window) = app.get_webview_window(label) {
+                    let handle = app.handle().clone();
+                    window.on_window_event(move |event| {
+                        if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, position }) = event {
+                            handle.emit("files-dropped", FilesDropped {
+                                window: label.to_string(),
+                                paths: paths.iter().map(|path| path.to_string_lossy().into_owned()).collect(),
+                                x: position.x,
+                                y: position.y,
+                            }).ok();
+                        }
+                    });
+                }
+            }
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                restore_mouse_sensitivity(&app.state::<MouseSettings>());
+            }
+        });
 }